@@ -0,0 +1,129 @@
+// Compares the paged Memory backend with the HashMap<i128, i128> RAM every
+// machine used to have. Run with `cargo bench --bench memory`.
+//
+// The workload is the real access trace of day 19's beam probes: every word
+// fetched, read and written while the program answers one grid query.
+
+use std::collections::HashMap;
+use std::fs;
+use std::hint::black_box;
+use std::time::Instant;
+use aoc2019::intcode::{Machine, Memory, get_instr, new_machine, parse_input, parse_instr, read, run_one_step};
+
+/// (parameter index, mode)
+type Param = (i128, i128);
+
+enum Access {
+    Read(i128),
+    Write(i128, i128),
+}
+
+trait Ram {
+    fn get(&self, address: i128) -> i128;
+    fn set(&mut self, address: i128, value: i128);
+}
+
+impl Ram for HashMap<i128, i128> {
+    fn get(&self, address: i128) -> i128 {
+        *HashMap::get(self, &address).unwrap_or(&0)
+    }
+
+    fn set(&mut self, address: i128, value: i128) {
+        self.insert(address, value);
+    }
+}
+
+impl Ram for Memory {
+    fn get(&self, address: i128) -> i128 {
+        Memory::get(self, address)
+    }
+
+    fn set(&mut self, address: i128, value: i128) {
+        Memory::set(self, address, value)
+    }
+}
+
+fn param_address(m: &Machine, index: i128, mode: i128) -> Option<i128> {
+    let raw = read(m, m.ip + index).ok()?;
+    match mode {
+        0 => Some(raw),
+        2 => Some(raw + m.base),
+        _ => None,
+    }
+}
+
+/// Runs one beam probe, logging the memory traffic of each instruction.
+fn record_trace(program: &Memory, inputs: &[i128], trace: &mut Vec<Access>) {
+    let mut m = new_machine(program.clone());
+    for &x in inputs {
+        m.push_input(x);
+    }
+    loop {
+        let (op, m1, m2, m3) = parse_instr(get_instr(&m).unwrap());
+        let (reads, write): (&[Param], Option<Param>) = match op {
+            1 | 2 | 7 | 8 => (&[(1, m1), (2, m2)], Some((3, m3))),
+            3 => (&[], Some((1, m1))),
+            4 | 9 => (&[(1, m1)], None),
+            5 | 6 => (&[(1, m1), (2, m2)], None),
+            _ => return,
+        };
+        let width = 1 + reads.len() as i128 + if write.is_some() { 1 } else { 0 };
+        for i in 0..width {
+            trace.push(Access::Read(m.ip + i));
+        }
+        for &(index, mode) in reads {
+            if let Some(address) = param_address(&m, index, mode) {
+                trace.push(Access::Read(address));
+            }
+        }
+        let target = write.and_then(|(index, mode)| param_address(&m, index, if mode == 2 { 2 } else { 0 }));
+        if run_one_step(&mut m).unwrap().is_some() && op != 4 {
+            return;
+        }
+        if let Some(address) = target {
+            trace.push(Access::Write(address, m.ram.get(address)));
+        }
+    }
+}
+
+fn replay<R: Ram>(ram: &mut R, trace: &[Access]) -> i128 {
+    let mut sum = 0;
+    for access in trace {
+        match *access {
+            Access::Read(address) => sum += ram.get(address),
+            Access::Write(address, value) => ram.set(address, value),
+        }
+    }
+    sum
+}
+
+fn time<R: Ram + Clone>(name: &str, ram: &R, trace: &[Access], rounds: usize) -> f64 {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        let mut fresh = ram.clone();
+        black_box(replay(&mut fresh, trace));
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let per_access = elapsed * 1e9 / (trace.len() * rounds) as f64;
+    println!("{:>8}: {:8.3} ms total, {:6.2} ns per access", name, elapsed * 1e3, per_access);
+    elapsed
+}
+
+fn main() {
+    let contents = fs::read_to_string("input19.txt")
+        .expect("File reading failed");
+    let program = parse_input(contents.trim());
+    let mut trace = Vec::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            record_trace(&program, &[i * 7, j * 7], &mut trace);
+        }
+    }
+    println!("{} accesses over 100 probes", trace.len());
+
+    let map: HashMap<i128, i128> = program.iter().collect();
+    let rounds = 20;
+    let hashed = time("HashMap", &map, &trace, rounds);
+    let paged = time("Memory", &program, &trace, rounds);
+    println!("Memory is {:.1}x faster", hashed / paged);
+}
@@ -0,0 +1,48 @@
+// Compares the interpreter with day 19's beam query compiled by `transpile`, on
+// the probes day 19 makes. Run with `cargo bench --bench transpile`.
+
+use std::fs;
+use std::hint::black_box;
+use std::time::Instant;
+use aoc2019::intcode::{Machine, Memory, new_machine, parse_input};
+
+#[allow(dead_code)]
+#[path = "../src/day19_beam.rs"]
+mod beam;
+
+/// Answers every probe of a `size` by `size` grid, counting the beamed cells.
+fn scan(size: i128, load: &dyn Fn() -> Machine, next_output: fn(&mut Machine) -> Option<i128>) -> i128 {
+    let mut beamed = 0;
+    for x in 0..size {
+        for y in 0..size {
+            let mut m = load();
+            m.push_input(x);
+            m.push_input(y);
+            beamed += next_output(&mut m).unwrap();
+        }
+    }
+    beamed
+}
+
+fn time(name: &str, load: &dyn Fn() -> Machine, next_output: fn(&mut Machine) -> Option<i128>, size: i128) -> f64 {
+    let start = Instant::now();
+    black_box(scan(size, load, next_output));
+    let elapsed = start.elapsed().as_secs_f64();
+    let per_probe = elapsed * 1e6 / (size * size) as f64;
+    println!("{:>11}: {:8.3} ms total, {:6.2} us per probe", name, elapsed * 1e3, per_probe);
+    elapsed
+}
+
+fn main() {
+    let contents = fs::read_to_string("input19.txt")
+        .expect("File reading failed");
+    let program: Memory = parse_input(contents.trim());
+    let size = 100;
+    let load_image = || new_machine(program.clone());
+    assert!(scan(size, &load_image, |m| m.next_output().unwrap())
+        == scan(size, &beam::new_machine, |m| beam::next_output(m).unwrap()));
+
+    let interpreted = time("interpreted", &load_image, |m| m.next_output().unwrap(), size);
+    let compiled = time("compiled", &beam::new_machine, |m| beam::next_output(m).unwrap(), size);
+    println!("compiled is {:.1}x faster", interpreted / compiled);
+}
@@ -0,0 +1,1312 @@
+//! Compiled from a 424 word Intcode image by `intcode::transpile`. Regenerate it rather than
+//! editing it.
+
+use aoc2019::intcode::{Divergence, Event, Input, IntcodeError, Limits, Machine, Memory, Output, differential, run_one_step};
+
+pub const IMAGE: [i128; 424] = [
+    109, 424, 203, 1, 21101, 11, 0, 0, 1105, 1, 282, 21102,
+    18, 1, 0, 1105, 1, 259, 2102, 1, 1, 221, 203, 1,
+    21102, 1, 31, 0, 1106, 0, 282, 21101, 38, 0, 0, 1105,
+    1, 259, 21001, 23, 0, 2, 21201, 1, 0, 3, 21101, 0,
+    1, 1, 21101, 0, 57, 0, 1105, 1, 303, 1201, 1, 0,
+    222, 20102, 1, 221, 3, 20101, 0, 221, 2, 21101, 259, 0,
+    1, 21102, 80, 1, 0, 1106, 0, 225, 21101, 127, 0, 2,
+    21102, 91, 1, 0, 1106, 0, 303, 1201, 1, 0, 223, 20102,
+    1, 222, 4, 21101, 259, 0, 3, 21101, 0, 225, 2, 21102,
+    225, 1, 1, 21102, 1, 118, 0, 1106, 0, 225, 21001, 222,
+    0, 3, 21101, 0, 89, 2, 21101, 133, 0, 0, 1105, 1,
+    303, 21202, 1, -1, 1, 22001, 223, 1, 1, 21101, 0, 148,
+    0, 1105, 1, 259, 2102, 1, 1, 223, 21002, 221, 1, 4,
+    21001, 222, 0, 3, 21101, 0, 21, 2, 1001, 132, -2, 224,
+    1002, 224, 2, 224, 1001, 224, 3, 224, 1002, 132, -1, 132,
+    1, 224, 132, 224, 21001, 224, 1, 1, 21102, 195, 1, 0,
+    106, 0, 108, 20207, 1, 223, 2, 20102, 1, 23, 1, 21102,
+    1, -1, 3, 21101, 0, 214, 0, 1105, 1, 303, 22101, 1,
+    1, 1, 204, 1, 99, 0, 0, 0, 0, 109, 5, 1201,
+    -4, 0, 249, 22102, 1, -3, 1, 21201, -2, 0, 2, 22101,
+    0, -1, 3, 21102, 250, 1, 0, 1105, 1, 225, 21202, 1,
+    1, -4, 109, -5, 2105, 1, 0, 109, 3, 22107, 0, -2,
+    -1, 21202, -1, 2, -1, 21201, -1, -1, -1, 22202, -1, -2,
+    -2, 109, -3, 2106, 0, 0, 109, 3, 21207, -2, 0, -1,
+    1206, -1, 294, 104, 0, 99, 22101, 0, -2, -2, 109, -3,
+    2106, 0, 0, 109, 5, 22207, -3, -4, -1, 1206, -1, 346,
+    22201, -4, -3, -4, 21202, -3, -1, -1, 22201, -4, -1, 2,
+    21202, 2, -1, -1, 22201, -4, -1, 1, 21201, -2, 0, 3,
+    21101, 0, 343, 0, 1106, 0, 303, 1105, 1, 415, 22207, -2,
+    -3, -1, 1206, -1, 387, 22201, -3, -2, -3, 21202, -2, -1,
+    -1, 22201, -3, -1, 3, 21202, 3, -1, -1, 22201, -3, -1,
+    2, 22101, 0, -4, 1, 21101, 384, 0, 0, 1106, 0, 303,
+    1105, 1, 415, 21202, -4, -1, -4, 22201, -4, -3, -4, 22202,
+    -3, -2, -2, 22202, -2, -4, -4, 22202, -3, -2, -3, 21202,
+    -4, -1, -2, 22201, -3, -2, 1, 21201, 1, 0, -4, 109,
+    -5, 2105, 1, 0,
+];
+
+pub fn new_machine() -> Machine {
+    aoc2019::intcode::new_machine(Memory::from(IMAGE.to_vec()))
+}
+
+/// Whether nothing checks or watches the machine's steps, so compiled code can take them.
+fn is_plain<I, O>(m: &Machine<I, O>) -> bool {
+    !m.checked && !m.strict && m.limits == Limits::default()
+        && m.trace.is_none() && m.profile.is_none() && m.code_watch.is_none()
+}
+
+/// Whether the cells from `start` up to `end` still hold the image's words.
+fn intact<I, O>(m: &Machine<I, O>, start: usize, end: usize) -> bool {
+    (start..end).all(|address| m.ram.get(address as i128) == IMAGE[address])
+}
+
+pub fn run_until_event<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    if !is_plain(m) {
+        return m.run_until_event();
+    }
+    loop {
+        match m.ip {
+            0 if intact(m, 0, 4) => {
+                // 0: arb #424
+                let a: i128 = 424;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 0; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 2: in rb+1
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 2; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                match m.input.read_input() {
+                    Some(value) => m.ram.set(to, value),
+                    None => { m.ip = 2; return Ok(Event::NeedsInput); }
+                }
+                m.steps += 1;
+                m.ip = 4;
+            }
+            4 if intact(m, 4, 11) => {
+                // 4: add #11, #0, rb+0
+                let a: i128 = 11;
+                let b: i128 = 0;
+                let to = m.base;
+                if to < 0 { m.ip = 4; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (8..11).contains(&to) { m.ip = 8; continue; }
+                // 8: jt #1, #282
+                m.steps += 1;
+                m.ip = 282;
+            }
+            11 if intact(m, 11, 18) => {
+                // 11: mul #18, #1, rb+0
+                let a: i128 = 18;
+                let b: i128 = 1;
+                let to = m.base;
+                if to < 0 { m.ip = 11; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (15..18).contains(&to) { m.ip = 15; continue; }
+                // 15: jt #1, #259
+                m.steps += 1;
+                m.ip = 259;
+            }
+            18 if intact(m, 18, 24) => {
+                // 18: mul #1, rb+1, [221]
+                let a: i128 = 1;
+                let at_b = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 18; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                m.ram.set(221, a.wrapping_mul(b));
+                m.steps += 1;
+                // 22: in rb+1
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 22; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                match m.input.read_input() {
+                    Some(value) => m.ram.set(to, value),
+                    None => { m.ip = 22; return Ok(Event::NeedsInput); }
+                }
+                m.steps += 1;
+                m.ip = 24;
+            }
+            24 if intact(m, 24, 31) => {
+                // 24: mul #1, #31, rb+0
+                let a: i128 = 1;
+                let b: i128 = 31;
+                let to = m.base;
+                if to < 0 { m.ip = 24; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (28..31).contains(&to) { m.ip = 28; continue; }
+                // 28: jf #0, #282
+                m.steps += 1;
+                m.ip = 282;
+            }
+            31 if intact(m, 31, 38) => {
+                // 31: add #38, #0, rb+0
+                let a: i128 = 38;
+                let b: i128 = 0;
+                let to = m.base;
+                if to < 0 { m.ip = 31; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (35..38).contains(&to) { m.ip = 35; continue; }
+                // 35: jt #1, #259
+                m.steps += 1;
+                m.ip = 259;
+            }
+            38 if intact(m, 38, 57) => {
+                // 38: add [23], #0, rb+2
+                let a = m.ram.get(23);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 38; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (42..57).contains(&to) { m.ip = 42; continue; }
+                // 42: add rb+1, #0, rb+3
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 42; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 42; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (46..57).contains(&to) { m.ip = 46; continue; }
+                // 46: add #0, #1, rb+1
+                let a: i128 = 0;
+                let b: i128 = 1;
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 46; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (50..57).contains(&to) { m.ip = 50; continue; }
+                // 50: add #0, #57, rb+0
+                let a: i128 = 0;
+                let b: i128 = 57;
+                let to = m.base;
+                if to < 0 { m.ip = 50; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (54..57).contains(&to) { m.ip = 54; continue; }
+                // 54: jt #1, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            57 if intact(m, 57, 80) => {
+                // 57: add rb+1, #0, [222]
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 57; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                m.ram.set(222, a.wrapping_add(b));
+                m.steps += 1;
+                // 61: mul #1, [221], rb+3
+                let a: i128 = 1;
+                let b = m.ram.get(221);
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 61; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (65..80).contains(&to) { m.ip = 65; continue; }
+                // 65: add #0, [221], rb+2
+                let a: i128 = 0;
+                let b = m.ram.get(221);
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 65; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (69..80).contains(&to) { m.ip = 69; continue; }
+                // 69: add #259, #0, rb+1
+                let a: i128 = 259;
+                let b: i128 = 0;
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 69; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (73..80).contains(&to) { m.ip = 73; continue; }
+                // 73: mul #80, #1, rb+0
+                let a: i128 = 80;
+                let b: i128 = 1;
+                let to = m.base;
+                if to < 0 { m.ip = 73; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (77..80).contains(&to) { m.ip = 77; continue; }
+                // 77: jf #0, #225
+                m.steps += 1;
+                m.ip = 225;
+            }
+            80 if intact(m, 80, 91) => {
+                // 80: add #127, #0, rb+2
+                let a: i128 = 127;
+                let b: i128 = 0;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 80; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (84..91).contains(&to) { m.ip = 84; continue; }
+                // 84: mul #91, #1, rb+0
+                let a: i128 = 91;
+                let b: i128 = 1;
+                let to = m.base;
+                if to < 0 { m.ip = 84; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (88..91).contains(&to) { m.ip = 88; continue; }
+                // 88: jf #0, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            91 if intact(m, 91, 118) => {
+                // 91: add rb+1, #0, [223]
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 91; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                m.ram.set(223, a.wrapping_add(b));
+                m.steps += 1;
+                // 95: mul #1, [222], rb+4
+                let a: i128 = 1;
+                let b = m.ram.get(222);
+                let to = match m.base.checked_add(4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 95; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (99..118).contains(&to) { m.ip = 99; continue; }
+                // 99: add #259, #0, rb+3
+                let a: i128 = 259;
+                let b: i128 = 0;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 99; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (103..118).contains(&to) { m.ip = 103; continue; }
+                // 103: add #0, #225, rb+2
+                let a: i128 = 0;
+                let b: i128 = 225;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 103; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (107..118).contains(&to) { m.ip = 107; continue; }
+                // 107: mul #225, #1, rb+1
+                let a: i128 = 225;
+                let b: i128 = 1;
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 107; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (111..118).contains(&to) { m.ip = 111; continue; }
+                // 111: mul #1, #118, rb+0
+                let a: i128 = 1;
+                let b: i128 = 118;
+                let to = m.base;
+                if to < 0 { m.ip = 111; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (115..118).contains(&to) { m.ip = 115; continue; }
+                // 115: jf #0, #225
+                m.steps += 1;
+                m.ip = 225;
+            }
+            118 if intact(m, 118, 133) => {
+                // 118: add [222], #0, rb+3
+                let a = m.ram.get(222);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 118; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (122..133).contains(&to) { m.ip = 122; continue; }
+                // 122: add #0, #89, rb+2
+                let a: i128 = 0;
+                let b: i128 = 89;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 122; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (126..133).contains(&to) { m.ip = 126; continue; }
+                // 126: add #133, #0, rb+0
+                let a: i128 = 133;
+                let b: i128 = 0;
+                let to = m.base;
+                if to < 0 { m.ip = 126; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (130..133).contains(&to) { m.ip = 130; continue; }
+                // 130: jt #1, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            133 if intact(m, 133, 148) => {
+                // 133: mul rb+1, #-1, rb+1
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 133; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 133; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (137..148).contains(&to) { m.ip = 137; continue; }
+                // 137: add [223], rb+1, rb+1
+                let a = m.ram.get(223);
+                let at_b = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 137; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 137; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (141..148).contains(&to) { m.ip = 141; continue; }
+                // 141: add #0, #148, rb+0
+                let a: i128 = 0;
+                let b: i128 = 148;
+                let to = m.base;
+                if to < 0 { m.ip = 141; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (145..148).contains(&to) { m.ip = 145; continue; }
+                // 145: jt #1, #259
+                m.steps += 1;
+                m.ip = 259;
+            }
+            148 if intact(m, 148, 195) => {
+                // 148: mul #1, rb+1, [223]
+                let a: i128 = 1;
+                let at_b = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 148; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                m.ram.set(223, a.wrapping_mul(b));
+                m.steps += 1;
+                // 152: mul [221], #1, rb+4
+                let a = m.ram.get(221);
+                let b: i128 = 1;
+                let to = match m.base.checked_add(4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 152; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (156..195).contains(&to) { m.ip = 156; continue; }
+                // 156: add [222], #0, rb+3
+                let a = m.ram.get(222);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 156; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (160..195).contains(&to) { m.ip = 160; continue; }
+                // 160: add #0, #21, rb+2
+                let a: i128 = 0;
+                let b: i128 = 21;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 160; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (164..195).contains(&to) { m.ip = 164; continue; }
+                // 164: add [132], #-2, [224]
+                let a = m.ram.get(132);
+                let b: i128 = -2;
+                m.ram.set(224, a.wrapping_add(b));
+                m.steps += 1;
+                // 168: mul [224], #2, [224]
+                let a = m.ram.get(224);
+                let b: i128 = 2;
+                m.ram.set(224, a.wrapping_mul(b));
+                m.steps += 1;
+                // 172: add [224], #3, [224]
+                let a = m.ram.get(224);
+                let b: i128 = 3;
+                m.ram.set(224, a.wrapping_add(b));
+                m.steps += 1;
+                // 176: mul [132], #-1, [132]
+                let a = m.ram.get(132);
+                let b: i128 = -1;
+                m.ram.set(132, a.wrapping_mul(b));
+                m.steps += 1;
+                // 180: add [224], [132], [224]
+                let a = m.ram.get(224);
+                let b = m.ram.get(132);
+                m.ram.set(224, a.wrapping_add(b));
+                m.steps += 1;
+                // 184: add [224], #1, rb+1
+                let a = m.ram.get(224);
+                let b: i128 = 1;
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 184; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (188..195).contains(&to) { m.ip = 188; continue; }
+                // 188: mul #195, #1, rb+0
+                let a: i128 = 195;
+                let b: i128 = 1;
+                let to = m.base;
+                if to < 0 { m.ip = 188; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (192..195).contains(&to) { m.ip = 192; continue; }
+                // 192: jf #0, [108]
+                let b = m.ram.get(108);
+                m.steps += 1;
+                m.ip = b;
+            }
+            195 if intact(m, 195, 214) => {
+                // 195: lt rb+1, [223], rb+2
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 195; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b = m.ram.get(223);
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 195; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, i128::from(a < b));
+                m.steps += 1;
+                if (199..214).contains(&to) { m.ip = 199; continue; }
+                // 199: mul #1, [23], rb+1
+                let a: i128 = 1;
+                let b = m.ram.get(23);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 199; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (203..214).contains(&to) { m.ip = 203; continue; }
+                // 203: mul #1, #-1, rb+3
+                let a: i128 = 1;
+                let b: i128 = -1;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 203; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (207..214).contains(&to) { m.ip = 207; continue; }
+                // 207: add #0, #214, rb+0
+                let a: i128 = 0;
+                let b: i128 = 214;
+                let to = m.base;
+                if to < 0 { m.ip = 207; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (211..214).contains(&to) { m.ip = 211; continue; }
+                // 211: jt #1, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            214 if intact(m, 214, 220) => {
+                // 214: add #1, rb+1, rb+1
+                let a: i128 = 1;
+                let at_b = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 214; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 214; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (218..220).contains(&to) { m.ip = 218; continue; }
+                // 218: out rb+1
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 218; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                m.output.write_output(a);
+                m.steps += 1;
+                m.ip = 220;
+                return Ok(Event::Output(a));
+            }
+            220 if intact(m, 220, 221) => {
+                // 220: hlt
+                m.ip = 220;
+                m.steps += 1;
+                return Ok(Event::Halted);
+            }
+            225 if intact(m, 225, 231) => {
+                // 225: arb #5
+                let a: i128 = 5;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 225; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 227: add rb-4, #0, [249]
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 227; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                m.ram.set(249, a.wrapping_add(b));
+                m.steps += 1;
+                m.ip = 231;
+            }
+            231 if intact(m, 231, 250) => {
+                // 231: mul #1, rb-3, rb+1
+                let a: i128 = 1;
+                let at_b = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 231; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 231; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (235..250).contains(&to) { m.ip = 235; continue; }
+                // 235: add rb-2, #0, rb+2
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 235; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 235; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (239..250).contains(&to) { m.ip = 239; continue; }
+                // 239: add #0, rb-1, rb+3
+                let a: i128 = 0;
+                let at_b = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 239; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 239; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (243..250).contains(&to) { m.ip = 243; continue; }
+                // 243: mul #250, #1, rb+0
+                let a: i128 = 250;
+                let b: i128 = 1;
+                let to = m.base;
+                if to < 0 { m.ip = 243; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (247..250).contains(&to) { m.ip = 247; continue; }
+                // 247: jt #1, #225
+                m.steps += 1;
+                m.ip = 225;
+            }
+            250 if intact(m, 250, 259) => {
+                // 250: mul rb+1, #1, rb-4
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 250; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 1;
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 250; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (254..259).contains(&to) { m.ip = 254; continue; }
+                // 254: arb #-5
+                let a: i128 = -5;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 254; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 256: jt #1, rb+0
+                let at_b = m.base;
+                if at_b < 0 { m.ip = 256; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                let b = m.ram.get(at_b);
+                m.steps += 1;
+                m.ip = b;
+            }
+            259 if intact(m, 259, 282) => {
+                // 259: arb #3
+                let a: i128 = 3;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 259; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 261: lt #0, rb-2, rb-1
+                let a: i128 = 0;
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 261; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 261; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, i128::from(a < b));
+                m.steps += 1;
+                if (265..282).contains(&to) { m.ip = 265; continue; }
+                // 265: mul rb-1, #2, rb-1
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 265; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 2;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 265; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (269..282).contains(&to) { m.ip = 269; continue; }
+                // 269: add rb-1, #-1, rb-1
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 269; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 269; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (273..282).contains(&to) { m.ip = 273; continue; }
+                // 273: mul rb-1, rb-2, rb-2
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 273; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 273; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 273; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (277..282).contains(&to) { m.ip = 277; continue; }
+                // 277: arb #-3
+                let a: i128 = -3;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 277; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 279: jf #0, rb+0
+                let at_b = m.base;
+                if at_b < 0 { m.ip = 279; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                let b = m.ram.get(at_b);
+                m.steps += 1;
+                m.ip = b;
+            }
+            282 if intact(m, 282, 291) => {
+                // 282: arb #3
+                let a: i128 = 3;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 282; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 284: lt rb-2, #0, rb-1
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 284; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 284; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, i128::from(a < b));
+                m.steps += 1;
+                if (288..291).contains(&to) { m.ip = 288; continue; }
+                // 288: jf rb-1, #294
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 288; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                m.steps += 1;
+                m.ip = if a == 0 { 294 } else { 291 };
+            }
+            291 if intact(m, 291, 293) => {
+                // 291: out #0
+                let a: i128 = 0;
+                m.output.write_output(a);
+                m.steps += 1;
+                m.ip = 293;
+                return Ok(Event::Output(a));
+            }
+            293 if intact(m, 293, 294) => {
+                // 293: hlt
+                m.ip = 293;
+                m.steps += 1;
+                return Ok(Event::Halted);
+            }
+            294 if intact(m, 294, 303) => {
+                // 294: add #0, rb-2, rb-2
+                let a: i128 = 0;
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 294; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 294; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (298..303).contains(&to) { m.ip = 298; continue; }
+                // 298: arb #-3
+                let a: i128 = -3;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 298; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 300: jf #0, rb+0
+                let at_b = m.base;
+                if at_b < 0 { m.ip = 300; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                let b = m.ram.get(at_b);
+                m.steps += 1;
+                m.ip = b;
+            }
+            303 if intact(m, 303, 312) => {
+                // 303: arb #5
+                let a: i128 = 5;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 303; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 305: lt rb-3, rb-4, rb-1
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 305; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 305; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 305; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, i128::from(a < b));
+                m.steps += 1;
+                if (309..312).contains(&to) { m.ip = 309; continue; }
+                // 309: jf rb-1, #346
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 309; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                m.steps += 1;
+                m.ip = if a == 0 { 346 } else { 312 };
+            }
+            312 if intact(m, 312, 343) => {
+                // 312: add rb-4, rb-3, rb-4
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 312; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 312; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 312; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (316..343).contains(&to) { m.ip = 316; continue; }
+                // 316: mul rb-3, #-1, rb-1
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 316; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 316; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (320..343).contains(&to) { m.ip = 320; continue; }
+                // 320: add rb-4, rb-1, rb+2
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 320; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 320; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 320; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (324..343).contains(&to) { m.ip = 324; continue; }
+                // 324: mul rb+2, #-1, rb-1
+                let at_a = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 324; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 324; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (328..343).contains(&to) { m.ip = 328; continue; }
+                // 328: add rb-4, rb-1, rb+1
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 328; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 328; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 328; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (332..343).contains(&to) { m.ip = 332; continue; }
+                // 332: add rb-2, #0, rb+3
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 332; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 332; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (336..343).contains(&to) { m.ip = 336; continue; }
+                // 336: add #0, #343, rb+0
+                let a: i128 = 0;
+                let b: i128 = 343;
+                let to = m.base;
+                if to < 0 { m.ip = 336; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (340..343).contains(&to) { m.ip = 340; continue; }
+                // 340: jf #0, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            343 if intact(m, 343, 346) => {
+                // 343: jt #1, #415
+                m.steps += 1;
+                m.ip = 415;
+            }
+            346 if intact(m, 346, 353) => {
+                // 346: lt rb-2, rb-3, rb-1
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 346; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 346; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 346; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, i128::from(a < b));
+                m.steps += 1;
+                if (350..353).contains(&to) { m.ip = 350; continue; }
+                // 350: jf rb-1, #387
+                let at_a = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 350; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                m.steps += 1;
+                m.ip = if a == 0 { 387 } else { 353 };
+            }
+            353 if intact(m, 353, 384) => {
+                // 353: add rb-3, rb-2, rb-3
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 353; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 353; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 353; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (357..384).contains(&to) { m.ip = 357; continue; }
+                // 357: mul rb-2, #-1, rb-1
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 357; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 357; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (361..384).contains(&to) { m.ip = 361; continue; }
+                // 361: add rb-3, rb-1, rb+3
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 361; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 361; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 361; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (365..384).contains(&to) { m.ip = 365; continue; }
+                // 365: mul rb+3, #-1, rb-1
+                let at_a = match m.base.checked_add(3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 365; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 365; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (369..384).contains(&to) { m.ip = 369; continue; }
+                // 369: add rb-3, rb-1, rb+2
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 369; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 369; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 369; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (373..384).contains(&to) { m.ip = 373; continue; }
+                // 373: add #0, rb-4, rb+1
+                let a: i128 = 0;
+                let at_b = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 373; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 373; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (377..384).contains(&to) { m.ip = 377; continue; }
+                // 377: add #384, #0, rb+0
+                let a: i128 = 384;
+                let b: i128 = 0;
+                let to = m.base;
+                if to < 0 { m.ip = 377; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (381..384).contains(&to) { m.ip = 381; continue; }
+                // 381: jf #0, #303
+                m.steps += 1;
+                m.ip = 303;
+            }
+            384 if intact(m, 384, 387) => {
+                // 384: jt #1, #415
+                m.steps += 1;
+                m.ip = 415;
+            }
+            387 if intact(m, 387, 415) => {
+                // 387: mul rb-4, #-1, rb-4
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 387; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 387; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (391..415).contains(&to) { m.ip = 391; continue; }
+                // 391: add rb-4, rb-3, rb-4
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 391; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 391; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 391; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (395..415).contains(&to) { m.ip = 395; continue; }
+                // 395: mul rb-3, rb-2, rb-2
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 395; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 395; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 395; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (399..415).contains(&to) { m.ip = 399; continue; }
+                // 399: mul rb-2, rb-4, rb-4
+                let at_a = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 399; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 399; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 399; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (403..415).contains(&to) { m.ip = 403; continue; }
+                // 403: mul rb-3, rb-2, rb-3
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 403; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 403; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 403; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (407..415).contains(&to) { m.ip = 407; continue; }
+                // 407: mul rb-4, #-1, rb-2
+                let at_a = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 407; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = -1;
+                let to = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 407; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_mul(b));
+                m.steps += 1;
+                if (411..415).contains(&to) { m.ip = 411; continue; }
+                // 411: add rb-3, rb-2, rb+1
+                let at_a = match m.base.checked_add(-3) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 411; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let at_b = match m.base.checked_add(-2) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 411; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let b = m.ram.get(at_b);
+                let to = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 411; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                m.ip = 415;
+            }
+            415 if intact(m, 415, 424) => {
+                // 415: add rb+1, #0, rb-4
+                let at_a = match m.base.checked_add(1) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 415; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                let a = m.ram.get(at_a);
+                let b: i128 = 0;
+                let to = match m.base.checked_add(-4) {
+                    Some(address) if address >= 0 => address,
+                    _ => { m.ip = 415; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.ram.set(to, a.wrapping_add(b));
+                m.steps += 1;
+                if (419..424).contains(&to) { m.ip = 419; continue; }
+                // 419: arb #-5
+                let a: i128 = -5;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 419; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 421: jt #1, rb+0
+                let at_b = m.base;
+                if at_b < 0 { m.ip = 421; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                let b = m.ram.get(at_b);
+                m.steps += 1;
+                m.ip = b;
+            }
+            _ => {
+                if let Some(event) = run_one_step(m)? {
+                    return Ok(event);
+                }
+            }
+        }
+    }
+}
+
+pub fn run<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    loop {
+        match run_until_event(m)? {
+            Event::Output(_) => {}
+            event => return Ok(event),
+        }
+    }
+}
+
+pub fn next_output<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Option<i128>, IntcodeError> {
+    match run_until_event(m)? {
+        Event::Output(value) => Ok(Some(value)),
+        _ => Ok(None),
+    }
+}
+
+/// Runs this module and the interpreter side by side on `input`.
+pub fn check(input: &[i128]) -> Result<Vec<Event>, Divergence> {
+    differential(&Memory::from(IMAGE.to_vec()), run_until_event, input)
+}
@@ -0,0 +1,87 @@
+//! Compiled from a 8 word Intcode image by `intcode::transpile`. Regenerate it rather than
+//! editing it.
+
+use aoc2019::intcode::{Divergence, Event, Input, IntcodeError, Limits, Machine, Memory, Output, differential, run_one_step};
+
+pub const IMAGE: [i128; 8] = [
+    109, -5, 1105, 1, 5, 204, 0, 99,
+];
+
+pub fn new_machine() -> Machine {
+    aoc2019::intcode::new_machine(Memory::from(IMAGE.to_vec()))
+}
+
+/// Whether nothing checks or watches the machine's steps, so compiled code can take them.
+fn is_plain<I, O>(m: &Machine<I, O>) -> bool {
+    !m.checked && !m.strict && m.limits == Limits::default()
+        && m.trace.is_none() && m.profile.is_none() && m.code_watch.is_none()
+}
+
+/// Whether the cells from `start` up to `end` still hold the image's words.
+fn intact<I, O>(m: &Machine<I, O>, start: usize, end: usize) -> bool {
+    (start..end).all(|address| m.ram.get(address as i128) == IMAGE[address])
+}
+
+pub fn run_until_event<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    if !is_plain(m) {
+        return m.run_until_event();
+    }
+    loop {
+        match m.ip {
+            0 if intact(m, 0, 5) => {
+                // 0: arb #-5
+                let a: i128 = -5;
+                m.base = match m.base.checked_add(a) {
+                    Some(base) => base,
+                    None => { m.ip = 0; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                };
+                m.steps += 1;
+                // 2: jt #1, #5
+                m.steps += 1;
+                m.ip = 5;
+            }
+            5 if intact(m, 5, 7) => {
+                // 5: out rb+0
+                let at_a = m.base;
+                if at_a < 0 { m.ip = 5; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }
+                let a = m.ram.get(at_a);
+                m.output.write_output(a);
+                m.steps += 1;
+                m.ip = 7;
+                return Ok(Event::Output(a));
+            }
+            7 if intact(m, 7, 8) => {
+                // 7: hlt
+                m.ip = 7;
+                m.steps += 1;
+                return Ok(Event::Halted);
+            }
+            _ => {
+                if let Some(event) = run_one_step(m)? {
+                    return Ok(event);
+                }
+            }
+        }
+    }
+}
+
+pub fn run<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    loop {
+        match run_until_event(m)? {
+            Event::Output(_) => {}
+            event => return Ok(event),
+        }
+    }
+}
+
+pub fn next_output<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Option<i128>, IntcodeError> {
+    match run_until_event(m)? {
+        Event::Output(value) => Ok(Some(value)),
+        _ => Ok(None),
+    }
+}
+
+/// Runs this module and the interpreter side by side on `input`.
+pub fn check(input: &[i128]) -> Result<Vec<Event>, Divergence> {
+    differential(&Memory::from(IMAGE.to_vec()), run_until_event, input)
+}
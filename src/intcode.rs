@@ -0,0 +1,1671 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{Sender, Receiver};
+use std::sync::mpsc;
+use std::thread;
+
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+pub mod code_watch;
+pub mod coverage;
+pub mod disasm;
+pub mod debugger;
+pub mod decompile;
+pub mod error;
+pub mod frame;
+pub mod io;
+pub mod limits;
+pub mod memory;
+pub mod pattern;
+pub mod profile;
+pub mod script;
+pub mod search;
+pub mod snapshot;
+pub mod trace;
+pub mod transcript;
+pub mod transpile;
+pub mod validate;
+pub mod word;
+
+pub use self::ascii::{Ascii, Screen};
+pub use self::asm::{AsmError, assemble, assemble_to_string};
+pub use self::cfg::{Block, Cfg, Exit};
+pub use self::code_watch::{CodeWatch, Patch};
+pub use self::coverage::Coverage;
+pub use self::debugger::{Debugger, Stop};
+pub use self::decompile::decompile;
+pub use self::disasm::{Instruction, Line, Operand, decode, disassemble, to_source};
+pub use self::error::IntcodeError;
+pub use self::frame::{FrameError, Frames, Packet, Paint, Tile};
+pub use self::io::{FromIter, Input, NonBlocking, OrDefault, Output};
+pub use self::limits::{Limit, Limits};
+pub use self::memory::Memory;
+pub use self::pattern::{Match, Pattern};
+pub use self::profile::Profile;
+pub use self::script::{Script, ScriptError, ScriptFailure, ScriptRun};
+pub use self::search::{Reached, bfs, try_input};
+pub use self::snapshot::{Snapshot, SnapshotError};
+pub use self::trace::{Filter, Io, Step, Trace, TraceError};
+pub use self::transcript::{Entry, Recorded, Recorder, Replay, ReplayError, Replayed, Transcript, TranscriptError, replay};
+pub use self::transpile::{Divergence, differential, transpile};
+pub use self::validate::{check_instr, preflight};
+pub use self::word::{Outcome, Word, compare_widths, run_as};
+
+/// Why `run_until_event` handed control back to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<W = i128> {
+    /// The input device had nothing to read. The ip is left on the input instruction.
+    NeedsInput,
+    /// A value was written to the output device.
+    Output(W),
+    Halted,
+    /// The next instruction would go past one of the machine's `limits`. It has not run.
+    Limit(Limit),
+}
+
+/// An Intcode computer with `W` words, reading from `I` and writing to `O`. By default
+/// input is queued with `push_input` and output is only reported through `Event::Output`.
+#[derive(Debug, Clone)]
+pub struct Machine<I = VecDeque<i128>, O = (), W = i128> {
+    pub id: i32,
+    pub ip: i128,
+    pub ram: Memory<W>,
+    pub input: I,
+    pub output: O,
+    pub base: i128,
+    /// Whether an `add` or `mul` that overflows a word faults with `IntcodeError::Overflow`.
+    /// Otherwise it wraps around.
+    pub checked: bool,
+    /// Whether every instruction is checked with `validate::check_instr` before it runs.
+    /// Otherwise unused mode digits are ignored and immediate mode writes act positional.
+    pub strict: bool,
+    pub limits: Limits,
+    /// Instructions executed so far.
+    pub steps: u64,
+    /// What has run since `start_trace`, if tracing.
+    pub trace: Option<Trace>,
+    /// Counts since `start_profile`, if profiling.
+    pub profile: Option<Profile>,
+    /// Writes into executed code since `start_code_watch`, if watching.
+    pub code_watch: Option<CodeWatch>,
+}
+
+impl<I: Input<W>, O: Output<W>, W: Word> Machine<I, O, W> {
+    /// Runs until the machine outputs a value, blocks on input or halts.
+    pub fn run_until_event(&mut self) -> Result<Event<W>, IntcodeError> {
+        loop {
+            if let Some(event) = run_one_step(self)? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Runs until the machine blocks on input or halts, leaving all output to the device.
+    pub fn run(&mut self) -> Result<Event<W>, IntcodeError> {
+        loop {
+            match self.run_until_event()? {
+                Event::Output(_) => {}
+                event => return Ok(event),
+            }
+        }
+    }
+
+    /// Runs until the next output. Gives `None` if the machine halts or needs input first.
+    pub fn next_output(&mut self) -> Result<Option<W>, IntcodeError> {
+        match self.run_until_event()? {
+            Event::Output(value) => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<I: Clone, O: Clone, W: Clone> Machine<I, O, W> {
+    /// An independent copy of the machine that carries on from the same state.
+    /// RAM is shared copy-on-write, so this costs little more than cloning the devices.
+    pub fn fork(&self) -> Machine<I, O, W> {
+        self.clone()
+    }
+}
+
+impl<O, W> Machine<VecDeque<W>, O, W> {
+    pub fn push_input(&mut self, value: W) {
+        self.input.push_back(value);
+    }
+}
+
+fn raw_instr<I, O, W: Word>(m: &Machine<I, O, W>) -> i128 {
+    if m.ip < 0 { 0 } else { word::saturate(&m.ram.get(m.ip)) }
+}
+
+/// `value` as an address, jump target or base offset.
+fn to_address<I, O, W: Word>(m: &Machine<I, O, W>, value: &W) -> Result<i128, IntcodeError> {
+    value.to_i128().ok_or_else(|| IntcodeError::Overflow { ip: m.ip, opcode: raw_instr(m) })
+}
+
+/// `address + offset`, or an overflow fault if that is past the end of the address space.
+fn offset_address<I, O, W: Word>(m: &Machine<I, O, W>, address: i128, offset: i128) -> Result<i128, IntcodeError> {
+    address.checked_add(offset).ok_or_else(|| IntcodeError::Overflow { ip: m.ip, opcode: raw_instr(m) })
+}
+
+pub fn read<I, O, W: Word>(m: &Machine<I, O, W>, index: i128) -> Result<W, IntcodeError> {
+    if index < 0 {
+        return Err(IntcodeError::NegativeAddress { ip: m.ip, opcode: raw_instr(m), address: index });
+    }
+    Ok(m.ram.get(index))
+}
+
+pub fn store<I, O, W: Word>(m: &mut Machine<I, O, W>, index: i128, value: W) -> Result<(), IntcodeError> {
+    if index < 0 {
+        return Err(IntcodeError::NegativeAddress { ip: m.ip, opcode: raw_instr(m), address: index });
+    }
+    m.ram.set(index, value);
+    Ok(())
+}
+
+pub fn get_index<I, O, W: Word>(m: &Machine<I, O, W>, index: i128, flag: i128) -> Result<W, IntcodeError> {
+    if flag == 1 {
+        // immediate value
+        get_immediate(m, index)
+    } else if flag == 0 {
+        // positional value
+        get_arg(m, index, 0)
+    } else if flag == 2 {
+        // relative value
+        get_arg(m, index, m.base)
+    } else {
+        Err(IntcodeError::BadMode { ip: m.ip, opcode: raw_instr(m), mode: flag })
+    }
+}
+
+/// Address written to by the parameter at `index`. Immediate mode is treated as positional.
+pub fn get_write_index<I, O, W: Word>(m: &Machine<I, O, W>, index: i128, flag: i128) -> Result<i128, IntcodeError> {
+    match flag {
+        0 | 1 => to_address(m, &get_immediate(m, index)?),
+        2 => offset_address(m, to_address(m, &get_immediate(m, index)?)?, m.base),
+        _ => Err(IntcodeError::BadMode { ip: m.ip, opcode: raw_instr(m), mode: flag }),
+    }
+}
+
+/// Address the instruction at the ip is about to write to, or `None` if it does not write.
+pub fn write_address<I, O, W: Word>(m: &Machine<I, O, W>) -> Result<Option<i128>, IntcodeError> {
+    let (opcode, m1, m2, m3) = parse_instr(get_instr(m)?);
+    match disasm::write_param(opcode) {
+        Some(i) => Ok(Some(get_write_index(m, i as i128 + 1, [m1, m2, m3][i])?)),
+        None => Ok(None),
+    }
+}
+
+pub fn get_arg<I, O, W: Word>(m: &Machine<I, O, W>, index: i128, offset: i128) -> Result<W, IntcodeError> {
+    read(m, offset_address(m, to_address(m, &get_immediate(m, index)?)?, offset)?)
+}
+
+pub fn get_immediate<I, O, W: Word>(m: &Machine<I, O, W>, index: i128) -> Result<W, IntcodeError> {
+    read(m, offset_address(m, m.ip, index)?)
+}
+
+pub fn parse_instr(instr: i128) -> (i128, i128, i128, i128) {
+    (instr % 100,
+        ((instr / 100) % 10),
+        ((instr / 1000) % 10),
+        ((instr / 10000) % 10))
+}
+
+/// The instruction word at the ip. A word too big for an `i128` is clamped, which never
+/// decodes to a valid instruction.
+pub fn get_instr<I, O, W: Word>(m: &Machine<I, O, W>) -> Result<i128, IntcodeError> {
+    Ok(word::saturate(&read(m, m.ip)?))
+}
+
+/// Fault for an `add` or `mul` whose result did not fit, if the machine is checked.
+fn overflow<I, O, W: Word>(m: &Machine<I, O, W>) -> IntcodeError {
+    IntcodeError::Overflow { ip: m.ip, opcode: raw_instr(m) }
+}
+
+pub fn do_addition<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, m2, m3) = parse_instr(get_instr(m)?);
+    let arg1: W = get_index(m, 1, m1)?;
+    let arg2 = get_index(m, 2, m2)?;
+    let output = get_write_index(m, 3, m3)?;
+    let sum = if m.checked {
+        arg1.checked_add(&arg2).ok_or_else(|| overflow(m))?
+    } else {
+        arg1.wrapping_add(&arg2)
+    };
+    store(m, output, sum)?;
+    m.ip += 4;
+    Ok(None)
+}
+
+pub fn do_multiplication<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, m2, m3) = parse_instr(get_instr(m)?);
+    let arg1: W = get_index(m, 1, m1)?;
+    let arg2 = get_index(m, 2, m2)?;
+    let output = get_write_index(m, 3, m3)?;
+    let product = if m.checked {
+        arg1.checked_mul(&arg2).ok_or_else(|| overflow(m))?
+    } else {
+        arg1.wrapping_mul(&arg2)
+    };
+    store(m, output, product)?;
+    m.ip += 4;
+    Ok(None)
+}
+
+pub fn do_input<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m)?);
+    let pos = get_write_index(m, 1, m1)?;
+    // check the address before taking a value off the input
+    read(m, pos)?;
+    let value = match m.input.read_input() {
+        Some(value) => value,
+        None => return Ok(Some(Event::NeedsInput)),
+    };
+    store(m, pos, value)?;
+    m.ip += 2;
+    Ok(None)
+}
+
+pub fn do_output<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m)?);
+    let value: W = get_index(m, 1, m1)?;
+    m.output.write_output(value.clone());
+    m.ip += 2;
+    Ok(Some(Event::Output(value)))
+}
+
+pub fn do_jmp<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>, jmp_if: bool) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, m2, _m3) = parse_instr(get_instr(m)?);
+    let arg1: W = get_index(m, 1, m1)?;
+    let arg2 = get_index(m, 2, m2)?;
+    // if jmp_if true then jmp when arg1 is non-zero
+    // if jmp_if false then jmp when arg1 is zero
+    if arg1.is_zero() != jmp_if {
+        m.ip = to_address(m, &arg2)?;
+    } else {
+        m.ip += 3;
+    }
+    Ok(None)
+}
+
+pub fn do_lt<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, m2, m3) = parse_instr(get_instr(m)?);
+    let arg1: W = get_index(m, 1, m1)?;
+    let arg2 = get_index(m, 2, m2)?;
+    let output = get_write_index(m, 3, m3)?;
+    store(m, output, W::from(if arg1 < arg2 { 1 } else { 0 }))?;
+    m.ip += 4;
+    Ok(None)
+}
+
+pub fn do_eq<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, m2, m3) = parse_instr(get_instr(m)?);
+    let arg1: W = get_index(m, 1, m1)?;
+    let arg2 = get_index(m, 2, m2)?;
+    let output = get_write_index(m, 3, m3)?;
+    store(m, output, W::from(if arg1 == arg2 { 1 } else { 0 }))?;
+    m.ip += 4;
+    Ok(None)
+}
+
+pub fn do_adjust_base<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m)?);
+    let arg1 = get_index(m, 1, m1)?;
+    m.base = offset_address(m, m.base, to_address(m, &arg1)?)?;
+    m.ip += 2;
+    Ok(None)
+}
+
+/// Executes one instruction, returning an event if the caller needs to act on it.
+/// On error nothing has been changed and the ip still points at the faulting instruction.
+pub fn run_one_step<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    if let Some(limit) = limits::exceeded(m) {
+        return Ok(Some(Event::Limit(limit)));
+    }
+    let event = if m.code_watch.is_some() {
+        code_watch::watched_step(m)?
+    } else {
+        run_unwatched(m)?
+    };
+    if event != Some(Event::NeedsInput) {
+        m.steps += 1;
+    }
+    Ok(event)
+}
+
+fn run_unwatched<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    if m.profile.is_some() {
+        return profile::profiled_step(m);
+    }
+    run_unprofiled(m)
+}
+
+fn run_unprofiled<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    if m.trace.is_some() {
+        return trace::traced_step(m);
+    }
+    execute(m)
+}
+
+/// Runs the instruction at the ip. Its opcode and every operand are read before it writes
+/// anything, and then the ip moves past it unless it jumps. So a write into the
+/// instruction's own cells, opcode included, only shows the next time they are fetched.
+fn execute<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let raw = get_instr(m)?;
+    if m.strict {
+        validate::check_instr(m.ip, raw)?;
+    }
+    let (instr, _m1, _m2, _m3) = parse_instr(raw);
+    if raw <= 0 {
+        return Err(IntcodeError::BadOpcode { ip: m.ip, opcode: raw });
+    }
+    // the ip has to be able to move past the instruction, unless it halts there
+    match disasm::opcode_info(instr) {
+        Some((_, count)) if instr != 99 => {
+            offset_address(m, m.ip, count as i128 + 1)?;
+        }
+        _ => {}
+    }
+    match instr {
+        1 => do_addition(m),
+        2 => do_multiplication(m),
+        3 => do_input(m),
+        4 => do_output(m),
+        5 => do_jmp(m, true),
+        6 => do_jmp(m, false),
+        7 => do_lt(m),
+        8 => do_eq(m),
+        9 => do_adjust_base(m),
+        99 => Ok(Some(Event::Halted)),
+        _ => Err(IntcodeError::BadOpcode { ip: m.ip, opcode: raw }),
+    }
+}
+
+pub type MachineResult<I, O, W = i128> = (Machine<I, O, W>, Result<Event<W>, IntcodeError>);
+
+/// Runs the machine on its own thread until it halts, faults or its input device runs dry.
+pub fn run_machine<I, O, W>(mut m: Machine<I, O, W>) -> thread::JoinHandle<MachineResult<I, O, W>>
+where
+    I: Input<W> + Send + 'static,
+    O: Output<W> + Send + 'static,
+    W: Word,
+{
+    thread::spawn(move || {
+        let result = m.run();
+        (m, result)
+    })
+}
+
+pub fn new_machine(program: Memory) -> Machine {
+    new_machine_with_io(program, VecDeque::new(), ())
+}
+
+pub fn new_machine_with_io<I: Input<W>, O: Output<W>, W: Word>(program: Memory<W>, input: I, output: O) -> Machine<I, O, W> {
+    Machine {
+        id: 0,
+        ip: 0,
+        ram: program,
+        input,
+        output,
+        base: 0,
+        checked: false,
+        strict: false,
+        limits: Limits::default(),
+        steps: 0,
+        trace: None,
+        profile: None,
+        code_watch: None,
+    }
+}
+
+pub type ChannelMachine = Machine<Receiver<i128>, Sender<i128>>;
+
+/// A machine that talks over channels, like the per-day VMs used to.
+pub fn new_channel_machine(program: Memory) -> (ChannelMachine, Sender<i128>, Receiver<i128>) {
+    let (my_input, input) = mpsc::channel();
+    let (output, my_output) = mpsc::channel();
+    (new_machine_with_io(program, input, output), my_input, my_output)
+}
+
+pub fn parse_input(input: &str) -> Memory {
+    let v: Vec<i128> = input.split(',').map(|x| x.trim().parse::<i128>().unwrap()).collect();
+    Memory::from(v)
+}
+
+/// Like `parse_input` with `W` words. `None` if a word is not a number or does not fit.
+pub fn parse_words<W: Word>(input: &str) -> Option<Memory<W>> {
+    let v: Option<Vec<W>> = input.split(',').map(|x| x.trim().parse::<W>().ok()).collect();
+    v.map(Memory::from)
+}
+
+pub fn test_machine(test_program: &str, test_input: i128, test_output: i128) {
+    let mut m = new_machine(parse_input(test_program));
+    m.push_input(test_input);
+    assert!(m.next_output() == Ok(Some(test_output)));
+    assert!(m.run_until_event() == Ok(Event::Halted));
+}
+
+pub fn run_test_quine() {
+    let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let mut m = new_machine(parse_input(program));
+    let mut results: Vec<String> = Vec::new();
+    while let Some(output) = m.next_output().unwrap() {
+        results.push(output.to_string());
+    }
+    let cmp = results.join(",");
+    assert!(cmp == program);
+}
+
+pub fn run_test_big() {
+    let program = "1102,34915192,34915192,7,4,7,99,0";
+    let mut m = new_machine(parse_input(program));
+    let output = m.next_output().unwrap().unwrap().to_string();
+    assert!(output.len() == 16);
+}
+
+pub fn run_test_events() {
+    let mut m = new_machine(parse_input("3,5,4,5,99,0"));
+    assert!(m.run_until_event() == Ok(Event::NeedsInput));
+    assert!(m.ip == 0);
+    assert!(m.run_until_event() == Ok(Event::NeedsInput));
+    m.push_input(42);
+    assert!(m.run_until_event() == Ok(Event::Output(42)));
+    assert!(m.run_until_event() == Ok(Event::Halted));
+    assert!(m.run_until_event() == Ok(Event::Halted));
+}
+
+pub fn run_test_threaded() {
+    let (mac, my_input, my_output) = new_channel_machine(parse_input("3,5,4,5,99,0"));
+    let handle = run_machine(mac);
+    my_input.send(7).unwrap();
+    assert!(my_output.recv().unwrap() == 7);
+    let (m, result) = handle.join().unwrap();
+    assert!(result == Ok(Event::Halted));
+    assert!(m.ip == 4);
+}
+
+pub fn run_test_devices() {
+    // echo each input back doubled until the input runs out
+    let program = parse_input("3,11,1002,11,2,11,4,11,1105,1,0,0");
+    let mut seen = Vec::new();
+    let mut m = new_machine_with_io(program.clone(), FromIter(vec![1, 2, 3].into_iter()), |x| seen.push(x));
+    assert!(m.run() == Ok(Event::NeedsInput));
+    drop(m);
+    assert!(seen == vec![2, 4, 6]);
+
+    let mut remaining = 2;
+    let next = move || {
+        remaining -= 1;
+        if remaining >= 0 { Some(5) } else { None }
+    };
+    let mut m = new_machine_with_io(program.clone(), next, Vec::new());
+    assert!(m.run() == Ok(Event::NeedsInput));
+    assert!(m.output == vec![10, 10]);
+
+    let (sender, receiver) = mpsc::channel();
+    sender.send(4).unwrap();
+    let input = OrDefault { inner: NonBlocking(receiver), default: -1 };
+    let mut m = new_machine_with_io(program, input, VecDeque::new());
+    assert!(m.next_output() == Ok(Some(8)));
+    assert!(m.next_output() == Ok(Some(-2)));
+    assert!(m.output == vec![8, -2]);
+}
+
+fn expect_error(program: &str, error: IntcodeError, message: &str) {
+    let mut m = new_machine(parse_input(program));
+    while m.ip != error.ip() {
+        run_one_step(&mut m).unwrap();
+    }
+    let ram = m.ram.clone();
+    let base = m.base;
+    assert!(m.run_until_event() == Err(error));
+    assert!(error.to_string() == message);
+    // the faulting instruction must leave the machine untouched
+    assert!(m.ip == error.ip() && m.base == base && m.ram == ram);
+}
+
+pub fn run_test_errors() {
+    expect_error("1101,1,1,5,42,0", IntcodeError::BadOpcode { ip: 4, opcode: 42 },
+        "bad opcode 42 at ip 4");
+    expect_error("-1", IntcodeError::BadOpcode { ip: 0, opcode: -1 },
+        "bad opcode -1 at ip 0");
+    expect_error("301,0,0,0,99", IntcodeError::BadMode { ip: 0, opcode: 301, mode: 3 },
+        "bad parameter mode 3 in 301 at ip 0");
+    expect_error("30001,0,0,0,99", IntcodeError::BadMode { ip: 0, opcode: 30001, mode: 3 },
+        "bad parameter mode 3 in 30001 at ip 0");
+    expect_error("109,-5,201,0,0,0,99",
+        IntcodeError::NegativeAddress { ip: 2, opcode: 201, address: -5 },
+        "negative address -5 used by 201 at ip 2");
+    expect_error("1105,1,-3", IntcodeError::NegativeAddress { ip: -3, opcode: 0, address: -3 },
+        "negative address -3 used by 0 at ip -3");
+    // address arithmetic that runs off the end of the address space faults too
+    let max = i128::MAX;
+    expect_error(&format!("109,{},204,1,99", max), IntcodeError::Overflow { ip: 2, opcode: 204 },
+        "overflow in 204 at ip 2");
+    expect_error(&format!("109,{},21101,1,1,1,99", max), IntcodeError::Overflow { ip: 2, opcode: 21101 },
+        "overflow in 21101 at ip 2");
+    expect_error(&format!("109,{},109,1,99", max), IntcodeError::Overflow { ip: 2, opcode: 109 },
+        "overflow in 109 at ip 2");
+    let program = format!("1101,104,0,{0},1101,7,0,{1},1105,1,{0}", max - 1, max);
+    expect_error(&program, IntcodeError::Overflow { ip: max - 1, opcode: 104 },
+        &format!("overflow in 104 at ip {}", max - 1));
+    let mut m = new_machine(parse_input(&format!("1105,1,{},99", max)));
+    m.ram.set(max, 99);
+    assert!(m.run() == Ok(Event::Halted));
+    // and so does it in checked mode
+    let mut m = new_machine(parse_input(&format!("109,{},204,1,99", max)));
+    m.checked = true;
+    assert!(m.run() == Err(IntcodeError::Overflow { ip: 2, opcode: 204 }));
+    // a bad input address must not swallow the input value
+    let mut m = new_machine(parse_input("109,-1,203,0,99"));
+    m.push_input(5);
+    assert!(m.run_until_event().is_err());
+    assert!(m.input.len() == 1);
+}
+
+pub fn run_test_memory() {
+    let mut ram = Memory::from(vec![1, 2, 3]);
+    assert!(ram.get(1) == 2);
+    assert!(ram.get(3) == 0);
+    assert!(ram.get(1_000_000_000_000) == 0);
+    ram.set(0, 7);
+    ram.set(5000, 9);
+    ram.set(1_000_000_000_000, -4);
+    // writing 0 to an untouched page must not allocate it
+    ram.set(900_000, 0);
+    assert!(ram.get(0) == 7 && ram.get(5000) == 9 && ram.get(1_000_000_000_000) == -4);
+    assert!(ram.get(5001) == 0 && ram.get(900_000) == 0);
+    assert!(ram.image_len() == 3 && ram.num_pages() == 2);
+    let cells: Vec<(i128, i128)> = ram.iter().collect();
+    assert!(cells == vec![(0, 7), (1, 2), (2, 3), (5000, 9), (1_000_000_000_000, -4)]);
+    // equality is by contents, not by layout
+    let mut other = Memory::new();
+    for &(address, value) in cells.iter().rev() {
+        other.set(address, value);
+    }
+    assert!(other == ram);
+    other.set(2, 0);
+    assert!(other != ram);
+    // negative addresses hold nothing, but touching them must not panic
+    ram.set(-5, 8);
+    ram.set(-1_000_000_000_000, 8);
+    assert!(ram.get(-5) == 0 && ram.get(-1_000_000_000_000) == 0);
+    assert!(ram.num_pages() == 2);
+    // programs that spill far past their image still work
+    let mut m = new_machine(parse_input("1101,5,6,100000,4,100000,99"));
+    assert!(m.next_output() == Ok(Some(11)));
+}
+
+pub fn run_test_snapshot() {
+    use std::time::Duration;
+
+    // doubles each input, stopping for more after every output
+    let program = parse_input("3,100,1002,100,2,100,4,100,1105,1,0,99");
+    let mut m = new_machine(program.clone());
+    m.base = 7;
+    m.push_input(5);
+    m.push_input(6);
+    assert!(m.next_output() == Ok(Some(10)));
+    let snap = m.snapshot();
+    let mut text = Vec::new();
+    snap.write_to(&mut text).unwrap();
+    assert!(String::from_utf8(text.clone()).unwrap() ==
+        "intcode-snapshot 2\nid 0\nip 8\nbase 7\nchecked 0\nstrict 0\nsteps 3\nlimits - - -\n\
+         input 1 6\nimage 12\nram 12\n\
+         0 3\n1 100\n2 1002\n3 100\n4 2\n5 100\n6 4\n7 100\n8 1105\n9 1\n11 99\n100 10\n");
+    let back = Snapshot::read_from(&text[..]).unwrap();
+    assert!(back == snap && back.ram.image_len() == 12);
+
+    // the restored machine carries on exactly like the original
+    let mut restored = back.restore();
+    restored.push_input(-1);
+    m.push_input(-1);
+    for _ in 0..3 {
+        let expected = m.run_until_event();
+        assert!(restored.run_until_event() == expected);
+    }
+    assert!(restored.run_until_event() == Ok(Event::NeedsInput));
+    assert!(restored.ip == m.ip && restored.base == m.base && restored.ram == m.ram);
+    assert!(restored.steps == m.steps);
+
+    // modes and limits come back too, and the limit still counts the steps taken before
+    let mut m = new_machine(program.clone());
+    m.checked = true;
+    m.strict = true;
+    m.limits = Limits { steps: Some(6), max_address: Some(200), max_cells: None, deadline: Limits::timeout(Duration::from_secs(60)) };
+    m.push_input(5);
+    assert!(m.next_output() == Ok(Some(10)));
+    let mut text = Vec::new();
+    m.snapshot().write_to(&mut text).unwrap();
+    assert!(String::from_utf8(text.clone()).unwrap().contains("checked 1\nstrict 1\nsteps 3\nlimits 6 200 -\n"));
+    let mut restored = Snapshot::read_from(&text[..]).unwrap().restore();
+    assert!(restored.checked && restored.strict && restored.limits.deadline.is_none());
+    restored.push_input(1);
+    assert!(restored.run_until_event() == Ok(Event::Limit(Limit::Steps)) && restored.steps == 6);
+
+    // cells far past the image and a machine id survive a trip through a file
+    let mut m = new_machine(program);
+    m.id = 3;
+    m.ram.set(1 << 40, -9);
+    let path = std::env::temp_dir().join(format!("intcode-snapshot-{}.txt", std::process::id()));
+    m.snapshot().save(&path).unwrap();
+    let loaded = Snapshot::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(loaded.id == 3 && loaded.ram.get(1 << 40) == -9 && loaded.ram == m.ram);
+    let mut seen = Vec::new();
+    let mut restored = loaded.restore_with_io(FromIter(vec![4].into_iter()), |x| seen.push(x));
+    assert!(restored.run() == Ok(Event::NeedsInput));
+    drop(restored);
+    assert!(seen == vec![8]);
+
+    // version 1 files still load, with every cell paged
+    let old = Snapshot::read_from(&b"intcode-snapshot 1\nid 2\nip 4\nbase 1\ninput 1 9\nram 2\n0 3\n5000 7\n"[..]).unwrap();
+    assert!(old.id == 2 && old.ip == 4 && old.base == 1 && old.input == vec![9] && !old.checked && old.steps == 0);
+    assert!(old.ram.get(0) == 3 && old.ram.get(5000) == 7 && old.ram.image_len() == 0);
+
+    let bad = |text: &str| Snapshot::read_from(text.as_bytes()).unwrap_err().to_string();
+    assert!(bad("intcode-snapshot 3\n") == "unsupported snapshot version 3 (expected at most 2)");
+    assert!(bad("intcode-snapshot 2\nid 0\nip 0\nbase 0\nchecked 2\n") ==
+        "malformed snapshot at line 5: expected 0 or 1 for checked, found 2");
+    assert!(bad("intcode-snapshot 2\nid 0\nip 0\nbase 0\nchecked 0\nstrict 0\nsteps 0\nlimits 5 -\n") ==
+        "malformed snapshot at line 8: expected 'limits <steps> <address> <cells>'");
+    assert!(bad("intcode-snapshot 1\nid 0\nip x\n") == "malformed snapshot at line 3: bad number 'x'");
+    assert!(bad("intcode-snapshot 1\nid 0\nip 0\nbase 0\ninput 2 1\n") ==
+        "malformed snapshot at line 5: expected 2 input values, found 1");
+    assert!(bad("intcode-snapshot 1\nid 0\nip 0\nbase 0\ninput 0\nram 2\n5 1\n") ==
+        "malformed snapshot at line 8: unexpected end of file");
+    assert!(bad("intcode-snapshot 1\nid 0\nip 0\nbase 0\ninput 0\nram 2\n5 1\n3 1\n") ==
+        "malformed snapshot at line 8: address 3 is out of order");
+}
+
+pub fn run_test_fork() {
+    let mut m = new_machine(parse_input("3,9,4,9,1105,1,0,99,0,0"));
+    m.push_input(1);
+    assert!(m.next_output() == Ok(Some(1)));
+    let mut fork = m.fork();
+    assert!(fork.ram.shares_image_with(&m.ram));
+    fork.push_input(2);
+    assert!(fork.next_output() == Ok(Some(2)));
+    // the fork copied the image when it wrote to it, the original never saw the write
+    assert!(!fork.ram.shares_image_with(&m.ram));
+    assert!(m.ram.get(9) == 1 && fork.ram.get(9) == 2);
+    m.push_input(3);
+    assert!(m.next_output() == Ok(Some(3)));
+    assert!(fork.ram.get(9) == 2);
+
+    // a corridor from 0 to 3: input 1 moves up, 2 moves down; answers 0 for a wall,
+    // 1 for a move and 2 on reaching the end, like the day15 droid
+    let corridor = parse_input(
+        "3,100,1008,100,1,101,1005,101,16,1001,102,-1,103,1105,1,20,\
+         1001,102,1,103,1007,103,0,101,1005,101,51,1007,103,4,101,1006,101,51,\
+         1001,103,0,102,1008,102,3,101,1001,101,1,101,4,101,1105,1,0,104,0,1105,1,0");
+    let start = new_machine(corridor);
+    let (_, output) = try_input(&start, 2).unwrap();
+    assert!(output == Some(0));
+    let mut goal = None;
+    let reached = bfs(start.fork(), 0, &[1, 2], |&pos, input, output| {
+        if output == 0 {
+            return None;
+        }
+        let next = if input == 1 { pos + 1 } else { pos - 1 };
+        if output == 2 {
+            goal = Some(next);
+        }
+        Some(next)
+    }).unwrap();
+    assert!(goal == Some(3));
+    let states: Vec<(i32, usize)> = reached.iter().map(|r| (r.state, r.steps)).collect();
+    assert!(states == vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+    assert!(reached[3].machine.ram.get(102) == 3 && start.ram.get(102) == 0);
+    // searching again from the end walks the whole corridor back
+    let back = bfs(reached[3].machine.fork(), 3, &[1, 2], |&pos, input, output| {
+        if output == 0 { None } else if input == 1 { Some(pos + 1) } else { Some(pos - 1) }
+    }).unwrap();
+    assert!(back.last().map(|r| (r.state, r.steps)) == Some((0, 3)));
+}
+
+pub fn run_test_disasm() {
+    let program = parse_input("109,-2,21101,5,7,3,204,3,1105,1,13,42,-7,1006,20,0,99,3,4");
+    let lines = disassemble(&program);
+    let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    assert!(text == vec![
+        "     0: arb #-2                      109,-2",
+        "     2: add #5, #7, rb+3             21101,5,7,3",
+        "     6: out rb+3                     204,3",
+        "     8: jt #1, #13                   1105,1,13",
+        "    11: .data                        42,-7",
+        "    13: jf [20], #0                  1006,20,0",
+        "    16: hlt                          99",
+        "    17: .data                        3,4",
+    ]);
+    match lines[1] {
+        Line::Instruction(ref instr) => {
+            assert!(instr.address == 2 && instr.opcode == 1 && instr.mnemonic == "add");
+            assert!(instr.operands == vec![Operand::Immediate(5), Operand::Immediate(7), Operand::Relative(3)]);
+            assert!(instr.size() == 4 && !instr.is_unconditional());
+        }
+        _ => panic!("expected an instruction at 2"),
+    }
+    assert!(decode(&program, 8).unwrap().jump_target() == Some(13));
+    assert!(decode(&program, 11).is_none());
+    // a call: the return address is pushed as an immediate, so code after the jump is kept
+    let program = parse_input("21101,0,7,0,1105,1,9,99,0,104,1,2105,1,0");
+    let text: Vec<String> = disassemble(&program).iter().map(|l| l.to_string()).collect();
+    assert!(text[2] == "     7: hlt                          99");
+    assert!(text[3] == "     8: .data                        0");
+    assert!(text[5] == "    11: jt #1, rb+0                  2105,1,0");
+}
+
+fn expect_asm_error(source: &str, message: &str) {
+    let error = assemble(source).unwrap_err();
+    assert!(error.to_string() == message);
+}
+
+pub fn run_test_asm() {
+    // every test_machine program survives a trip through the disassembler and back
+    let programs = [
+        "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        "104,-1125899906842624,99",
+        "109,1,204,-1,99",
+        "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        "1102,34915192,34915192,7,4,7,99,0",
+    ];
+    for program in programs.iter() {
+        let words = parse_input(program);
+        let source = to_source(&disassemble(&words));
+        assert!(assemble_to_string(&source).unwrap() == *program);
+    }
+
+    // the quine, written by hand
+    let quine = "
+    start:
+        arb #1                  ; step the base on to the next word
+        out rb-1                ; and print the one before it
+        add [100], #1, [100]
+        eq [100], #end, [101]
+        jf [101], #start
+        hlt
+    end:
+    ";
+    assert!(assemble_to_string(quine).unwrap() == "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+
+    let source = "
+        ; greet, then halt
+        jt #1, #start
+    msg: .string \"hi\\n\"
+    len: .data end-msg, -1, msg+1
+    start:
+        out [msg]
+        hlt
+    end:
+    ";
+    let words = assemble(source).unwrap();
+    assert!(words == vec![1105, 1, 9, 104, 105, 10, 9, -1, 4, 4, 3, 99]);
+    let mut m = new_machine(Memory::from(words));
+    assert!(m.next_output() == Ok(Some(104)));
+
+    expect_asm_error("add #1, #2", "1:11: expected ',', found end of line");
+    expect_asm_error("  mov #1, [2]", "1:3: unknown mnemonic 'mov'");
+    expect_asm_error("out [x]\nhlt", "1:6: undefined label 'x'");
+    expect_asm_error("a: hlt\na: hlt", "2:1: label 'a' is defined twice");
+    expect_asm_error("out #1 #2", "1:8: expected end of line, found '#'");
+    expect_asm_error("jt rb+, #0", "1:7: expected a number or label, found ','");
+    expect_asm_error(".string \"abc", "1:9: unterminated string");
+    expect_asm_error("out 5", "1:5: expected an operand ('[a]', '#v' or 'rb+o'), found '5'");
+    expect_asm_error(".data 12x", "1:7: bad number '12x'");
+}
+
+pub fn run_test_debugger() {
+    // echo each input back doubled until the input runs out
+    let program = parse_input("3,11,1002,11,2,11,4,11,1105,1,0,0");
+    let mut d = Debugger::new(new_machine(program.clone()));
+    assert!(d.cont() == Stop::NeedsInput && d.machine.ip == 0);
+    assert!(d.execute("input 5, 6").unwrap() == "2 values queued");
+    assert!(d.execute("b 8").unwrap() == "breakpoint at 8");
+    assert!(d.execute("c").unwrap() == "output: 10\nbreakpoint at 8\n     8: jt #1, #0                    1105,1,0");
+    // continuing from a breakpoint steps over it
+    assert!(d.cont() == Stop::Breakpoint(8));
+    assert!(d.output == vec![12]);
+    assert!(d.execute("regs").unwrap() == "ip 8 base 0\n     8: jt #1, #0                    1105,1,0");
+    assert!(d.execute("s 2").unwrap() == "output: 12\nwaiting for input\n     0: in [11]                      3,11");
+    assert!(d.execute("x 9 4").unwrap() == "     9: 1 0 12 0");
+    assert!(d.execute("l 6 3").unwrap() ==
+        "       6: out [11]                     4,11\n       8: jt #1, #0                    1105,1,0\n      11: .data                        12");
+    assert!(d.execute("b").unwrap() == "usage: b <address>");
+    assert!(d.execute("x zz").unwrap() == "bad number in 'zz'");
+    assert!(d.execute("frob").unwrap() == "unknown command 'frob', try help");
+    assert!(d.execute("quit").is_none());
+
+    let mut d = Debugger::new(new_machine(program));
+    assert!(d.execute("watch 11").unwrap() == "watching [11] = 0");
+    assert!(d.execute("line A").unwrap() == "2 values queued");
+    assert!(d.cont() == Stop::Watchpoint { ip: 0, address: 11, old: 0, new: 65 });
+    assert!(d.execute("c").unwrap() ==
+        "watchpoint [11]: 65 -> 130 by ip 2\n     6: out [11]                     4,11");
+    assert!(d.execute("unwatch 11").unwrap() == "stopped watching [11]");
+    assert!(d.execute("c").unwrap() == "output: 130,20\nwaiting for input\n     0: in [11]                      3,11");
+    assert!(d.execute("l 0 1").unwrap() == "=>     0: in [11]                      3,11");
+
+    // text output is shown as text
+    let mut d = Debugger::new(new_machine(parse_input("104,72,104,105,104,10,99")));
+    assert!(d.execute("c").unwrap() == "output:\nHi\nhalted\n     6: hlt                          99");
+    let mut d = Debugger::new(new_machine(parse_input("1101,1,1,5,42,0")));
+    assert!(d.cont() == Stop::Error(IntcodeError::BadOpcode { ip: 4, opcode: 42 }));
+}
+
+pub fn run_test_debugger_addresses() {
+    // negative addresses are refused and the session carries on
+    let mut d = Debugger::new(new_machine(parse_input("1101,1,1,100,99")));
+    assert!(d.execute("c").unwrap() == "halted\n     4: hlt                          99");
+    assert!(d.execute("x -5").unwrap() == "negative address -5");
+    assert!(d.execute("watch -1").unwrap() == "negative address -1");
+    assert!(d.execute("l -3 2").unwrap() == "negative address -3");
+    assert!(d.watchpoints.is_empty());
+    assert!(d.execute("x 100 1").unwrap() == "   100: 2");
+
+    // so is running off either end of the address space
+    let max = i128::MAX;
+    assert!(d.execute(&format!("x {} 3", max)).unwrap() == format!("{}: 0", max));
+    assert!(d.execute(&format!("l {} 3", max)).unwrap() == format!("  {}: .data                        0", max));
+    let mut d = Debugger::new(new_machine(parse_input("1105,1,-3")));
+    assert!(d.execute("c").unwrap() ==
+        "error: negative address -3 used by 0 at ip -3\n    -3: negative address");
+    assert!(d.execute("regs").unwrap() == "ip -3 base 0\n    -3: negative address");
+}
+
+pub fn run_test_trace() {
+    let program = parse_input("109,-2,3,11,1002,11,2,11,4,11,99,0");
+    let mut m = new_machine(program.clone());
+    m.start_trace();
+    assert!(m.run_until_event() == Ok(Event::NeedsInput));
+    m.push_input(5);
+    assert!(m.next_output() == Ok(Some(10)));
+    assert!(m.run_until_event() == Ok(Event::Halted));
+    let trace = m.take_trace().unwrap();
+    assert!(m.trace.is_none());
+    // the input wait is not a step
+    assert!(trace.steps.len() == 5);
+    assert!(trace.steps[1] == Step {
+        ip: 2, instr: 3, operands: vec![11], write: Some((11, 5)), base: None, io: Some(Io::In(5))
+    });
+    let mut text = Vec::new();
+    trace.write_to(&mut text).unwrap();
+    assert!(String::from_utf8(text.clone()).unwrap() ==
+        "intcode-trace 1\n0 109 -2 b-2\n2 3 11 w11=5 i5\n4 1002 5,2,11 w11=10\n8 4 10 o10\n10 99 -\n");
+    assert!(Trace::read_from(&text[..]).unwrap() == trace);
+
+    let shown: Vec<String> = trace.steps.iter().map(|s| s.to_string()).collect();
+    assert!(shown[0] == "     0: arb  -2                             rb <- -2");
+    assert!(shown[2] == "     4: mul  5, 2, [11]                     [11] <- 10");
+    assert!(shown[4] == "    10: hlt");
+    let filter = Filter { range: Some((2, 8)), kinds: vec!["in".to_string(), "out".to_string()] };
+    let ips: Vec<i128> = trace.filter(&filter).map(|s| s.ip).collect();
+    assert!(ips == vec![2, 8]);
+
+    // tracing does not change what the program does
+    let quine = parse_input("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+    let mut plain = new_machine_with_io(quine.clone(), VecDeque::new(), Vec::new());
+    let mut traced = new_machine_with_io(quine, VecDeque::new(), Vec::new());
+    traced.start_trace();
+    assert!(plain.run() == traced.run());
+    assert!(plain.output == traced.output && plain.ram == traced.ram);
+    assert!(traced.trace.unwrap().steps.len() == 16 * 5 + 1);
+
+    // nor how it faults, even with the ip gone negative
+    let mut traced = new_machine(parse_input("1105,1,-3"));
+    traced.start_trace();
+    assert!(traced.run() == Err(IntcodeError::NegativeAddress { ip: -3, opcode: 0, address: -3 }));
+    assert!(traced.trace.unwrap().steps.len() == 1);
+
+    let bad = |text: &str| Trace::read_from(text.as_bytes()).unwrap_err().to_string();
+    assert!(bad("") == "malformed trace at line 1: missing header");
+    assert!(bad("intcode-trace 9\n") == "unsupported trace version 9 (expected at most 1)");
+    assert!(bad("intcode-trace 1\n0 109 -2 x4\n") == "malformed trace at line 2: unknown field 'x4'");
+}
+
+pub fn run_test_reverse() {
+    // read x, y := x * 3, base += y, print y, then spoil x and halt
+    let program = parse_input("3,100,1002,100,3,101,9,101,4,101,1101,0,-1,100,99");
+    let fresh = new_machine(program.clone());
+    let mut d = Debugger::new(new_machine(program));
+    d.machine.push_input(7);
+    d.machine.push_input(8);
+    assert!(d.cont() == Stop::Halted);
+    assert!(d.output == vec![21] && d.machine.ram.get(100) == -1 && d.machine.base == 21);
+    assert!(d.history_len() == 5);
+
+    // rewind to the instruction that last wrote [100]
+    d.watch(100);
+    assert!(d.reverse_continue() == Stop::Watchpoint { ip: 10, address: 100, old: 7, new: -1 });
+    assert!(d.machine.ip == 10 && d.machine.ram.get(100) == 7);
+    // and then to the one before it, which read it from input
+    assert!(d.reverse_continue() == Stop::Watchpoint { ip: 0, address: 100, old: 0, new: 7 });
+    assert!(d.output.is_empty() && d.machine.base == 0);
+    assert!(d.machine.input == vec![7, 8]);
+    assert!(d.machine.ip == fresh.ip && d.machine.ram == fresh.ram);
+    assert!(d.step_back() == Some(Stop::StartOfHistory));
+
+    // replaying gives the same run, and breakpoints stop it in both directions
+    d.execute("unwatch 100");
+    assert!(d.execute("b 8").unwrap() == "breakpoint at 8");
+    assert!(d.cont() == Stop::Breakpoint(8));
+    assert!(d.execute("s").unwrap() == "output: 21\n    10: add #0, #-1, [100]           1101,0,-1,100");
+    assert!(d.execute("rc").unwrap() == "breakpoint at 8\n     8: out [101]                    4,101");
+    assert!(d.execute("back 5").unwrap() == "at start of history\n     0: in [100]                     3,100");
+    assert!(d.history_len() == 0);
+    assert!(d.cont() == Stop::Breakpoint(8));
+    assert!(d.execute("sb 1").unwrap() == "     6: arb [101]                    9,101");
+
+    // a shallow history forgets the oldest instructions and says so when it runs out
+    let mut d = Debugger::new(new_machine(parse_input("3,100,1002,100,3,101,9,101,4,101,1101,0,-1,100,99")));
+    d.history_depth = 2;
+    d.machine.push_input(7);
+    assert!(d.cont() == Stop::Halted && d.history_len() == 2 && d.history_dropped() == 3);
+    assert!(d.step_back_n(5) == Stop::HistoryDropped(3));
+    assert!(d.machine.ip == 8 && d.machine.ram.get(100) == 7);
+    assert!(d.execute("sb").unwrap() ==
+        "at oldest kept history, 3 earlier instructions dropped\n     8: out [101]                    4,101");
+    assert!(d.execute("info").unwrap() == "breakpoints: \nwatchpoints: \nhistory: 0 kept, 3 dropped, depth 2");
+    assert!(d.cont() == Stop::Halted && d.history_len() == 2 && d.history_dropped() == 3);
+}
+
+pub fn run_test_profile() {
+    let program = Memory::from(assemble("
+        arb #100                ; main's frame
+        add #0, #3, rb+0        ; n = 3
+    loop:
+        jf rb+0, #done
+        add #ret, #0, rb+1      ; call func
+        jt #1, #func
+    ret:
+        add rb+0, #-1, rb+0
+        jt #1, #loop
+    done:
+        out rb+0
+        hlt
+    func:
+        arb #2
+        out rb-1                ; print the return address
+        arb #-2
+        jt #1, rb+1
+    ").unwrap());
+    let mut m = new_machine_with_io(program.clone(), VecDeque::new(), Vec::new());
+    m.start_profile();
+    assert!(m.run() == Ok(Event::Halted));
+    assert!(m.output == vec![16, 16, 16, 0]);
+    let profile = m.take_profile().unwrap();
+    assert!(profile.total == 32);
+    assert!(profile.by_opcode[&4] == 4 && profile.by_opcode[&9] == 7);
+    assert!(profile.by_address[&6] == 4 && profile.by_address[&26] == 3);
+    assert!(profile.branches[&6] == (1, 3) && profile.branches[&32] == (3, 0));
+    assert!(profile.reads[&100] == 8 && profile.writes[&100] == 4);
+    assert!(profile.reads[&101] == 6 && profile.writes[&101] == 3);
+    // func's frame opens at its arb and closes at the arb that leaves it
+    assert!(profile.folded() == "main;f0 23\nmain;f0;f26 9\n");
+    let report = profile.report(&program, 3);
+    assert!(report.starts_with("32 instructions executed\n\nby opcode:\n  jt              9  28.12%\n"));
+    assert!(report.contains("\nhot spots:\n       6:            4  12.50%  jf rb+0, #23\n"));
+    assert!(report.ends_with("hot cells (reads / writes):\n  [100]: 8 / 4\n  [101]: 6 / 3\n"));
+
+    // profiling does not change what the program does, and can be traced at the same time
+    let mut traced = new_machine_with_io(program, VecDeque::new(), Vec::new());
+    traced.start_profile();
+    traced.start_trace();
+    assert!(traced.run() == Ok(Event::Halted) && traced.output == m.output);
+    assert!(traced.take_trace().unwrap().steps.len() == 32);
+    assert!(traced.take_profile().unwrap() == profile);
+
+    // nor how it faults, whether on a negative ip or on a relative address that overflows
+    let mut m = new_machine(parse_input("1105,1,-3"));
+    m.start_profile();
+    assert!(m.run() == Err(IntcodeError::NegativeAddress { ip: -3, opcode: 0, address: -3 }));
+    let mut m = new_machine(parse_input(&format!("109,{},204,1,99", i128::MAX)));
+    m.start_profile();
+    assert!(m.run() == Err(IntcodeError::Overflow { ip: 2, opcode: 204 }));
+    assert!(m.take_profile().unwrap().total == 1);
+}
+
+pub fn run_test_coverage() {
+    let program = Memory::from(assemble("
+        in [x]
+        jt [x], #odd
+        out #0
+        hlt
+    odd:
+        out #1
+        hlt
+    x:
+        .data 0
+    ").unwrap());
+    let run = |input: i128| {
+        let mut m = new_machine_with_io(program.clone(), VecDeque::from(vec![input]), Vec::new());
+        m.start_profile();
+        assert!(m.run() == Ok(Event::Halted));
+        m.take_profile().unwrap()
+    };
+    let mut coverage = Coverage::default();
+    coverage.add(&run(0));
+    assert!(coverage.hits[&0] == 1 && coverage.hits[&5] == 1 && !coverage.hits.contains_key(&8));
+    let listing = coverage.listing(&program);
+    assert!(listing.len() == 7);
+    let lcov = coverage.lcov(&listing, "prog.lst");
+    assert!(lcov.starts_with("TN:\nSF:prog.lst\nDA:1,1\nBRDA:2,0,0,0\nBRDA:2,0,1,1\nDA:2,1\nDA:3,1\n"));
+    assert!(lcov.ends_with("DA:5,0\nDA:6,0\nBRF:2\nBRH:1\nLF:6\nLH:4\nend_of_record\n"));
+
+    // a second run covers the other side of the branch
+    coverage.add(&run(1));
+    assert!(coverage.branches[&2] == (1, 1) && coverage.hits[&0] == 2);
+    let lcov = coverage.lcov(&coverage.listing(&program), "prog.lst");
+    assert!(lcov.ends_with("BRF:2\nBRH:2\nLF:6\nLH:6\nend_of_record\n"));
+}
+
+pub fn run_test_words() {
+    use num_bigint::BigInt;
+
+    let big = "1102,34915192,34915192,7,4,7,99,0";
+    let mut m: Machine<VecDeque<i64>, (), i64> = new_machine_with_io(parse_words(big).unwrap(), VecDeque::new(), ());
+    assert!(m.next_output() == Ok(Some(1219070632396864)));
+    // unchecked machines wrap, checked ones fault and leave the machine as it was
+    let mut m = new_machine_with_io(parse_words::<i32>(big).unwrap(), VecDeque::new(), ());
+    assert!(m.next_output() == Ok(Some(2112)));
+    let mut m = new_machine_with_io(parse_words::<i32>(big).unwrap(), VecDeque::new(), ());
+    m.checked = true;
+    assert!(m.next_output() == Err(IntcodeError::Overflow { ip: 0, opcode: 1102 }));
+    assert!(m.ip == 0 && m.ram.get(7) == 0);
+
+    // squaring 2^100 overflows an i128 but not a bignum
+    let square = "2,7,7,7,4,7,99,1267650600228229401496703205376";
+    let squared = "1606938044258990275541962092341162602522202993782792835301376";
+    let mut m = new_machine(parse_input(square));
+    m.checked = true;
+    assert!(m.next_output() == Err(IntcodeError::Overflow { ip: 0, opcode: 2 }));
+    let mut m = new_machine_with_io(parse_words::<BigInt>(square).unwrap(), VecDeque::new(), Vec::new());
+    m.checked = true;
+    assert!(m.run() == Ok(Event::Halted));
+    assert!(m.output[0].to_string() == squared);
+    // but a bignum address still has to fit in an i128
+    let jump = format!("1105,1,{},99", squared);
+    let mut m = new_machine_with_io(parse_words::<BigInt>(&jump).unwrap(), VecDeque::new(), ());
+    assert!(m.run() == Err(IntcodeError::Overflow { ip: 0, opcode: 1105 }));
+
+    // the quine outputs itself under every width
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let widths = compare_widths(quine, &[]);
+    assert!(widths.iter().map(|(name, _)| *name).collect::<Vec<_>>() == vec!["i32", "i64", "i128", "bignum"]);
+    for (_, outcome) in &widths {
+        assert!(*outcome == Outcome::Ran { output: quine.split(',').map(String::from).collect(), stop: Ok(Event::Halted) });
+    }
+    let widths = compare_widths("3,9,1002,9,1000000,9,4,9,99,0", &["1000000"]);
+    assert!(widths[0].1 == Outcome::Ran { output: vec![], stop: Err(IntcodeError::Overflow { ip: 2, opcode: 1002 }) });
+    for (_, outcome) in &widths[1..] {
+        assert!(*outcome == Outcome::Ran { output: vec!["1000000000000".to_string()], stop: Ok(Event::Halted) });
+    }
+    assert!(compare_widths(big, &["99999999999"])[0].1 == Outcome::DoesNotFit);
+}
+
+pub fn run_test_limits() {
+    use std::time::Duration;
+
+    // a machine stopped by a limit is left before the instruction, and resumes once given more room
+    let mut m = new_machine(parse_input("1105,1,0"));
+    m.limits.steps = Some(100);
+    assert!(m.run() == Ok(Event::Limit(Limit::Steps)));
+    assert!(m.steps == 100 && m.ip == 0);
+    m.limits.steps = Some(150);
+    assert!(m.run_until_event() == Ok(Event::Limit(Limit::Steps)));
+    assert!(m.steps == 150);
+
+    let mut m = new_machine(parse_input("1105,1,0"));
+    m.limits.deadline = Limits::timeout(Duration::from_millis(20));
+    let (m, result) = run_machine(m).join().unwrap();
+    assert!(result == Ok(Event::Limit(Limit::Time)));
+    assert!(m.steps > 0 && m.steps % limits::TIME_CHECK_INTERVAL == 0);
+
+    let mut m = new_machine(parse_input("1101,1,1,1000,99"));
+    m.limits.max_address = Some(999);
+    assert!(m.run() == Ok(Event::Limit(Limit::Address(1000))));
+    assert!(m.ip == 0 && m.ram.get(1000) == 0 && m.steps == 0);
+    m.limits.max_address = Some(1000);
+    assert!(m.run() == Ok(Event::Halted) && m.ram.get(1000) == 2);
+
+    // the image is 5 cells, and writing far past it allocates a whole page
+    let mut m = new_machine(parse_input("1101,1,1,5000,99"));
+    m.limits.max_cells = Some(1000);
+    assert!(m.run() == Ok(Event::Limit(Limit::Cells)));
+    assert!(m.ram.num_pages() == 0);
+    m.limits.max_cells = Some(5 + memory::PAGE_SIZE as usize);
+    assert!(m.run() == Ok(Event::Halted) && m.ram.cells() == 1029);
+
+    // an instruction that faults reports its error rather than a limit
+    let mut m = new_machine(parse_input("1101,1,1,-1,99"));
+    m.limits.max_address = Some(10);
+    assert!(m.run() == Err(IntcodeError::NegativeAddress { ip: 0, opcode: 1101, address: -1 }));
+}
+
+pub fn run_test_transcript() {
+    // echo each input back doubled until the input runs out
+    let program = parse_input("3,11,1002,11,2,11,4,11,1105,1,0,0");
+    let recorder = Recorder::new();
+    for id in 0..2 {
+        let input = recorder.record(id, FromIter(vec![1 + id as i128, 3].into_iter()));
+        let mut m = new_machine_with_io(program.clone(), input, recorder.record(id, Vec::new()));
+        assert!(m.run() == Ok(Event::NeedsInput));
+        assert!(m.output.inner == vec![2 + 2 * id as i128, 6]);
+    }
+    let transcript = recorder.transcript();
+    let ios: Vec<(i32, Io)> = transcript.entries.iter().map(|entry| (entry.machine, entry.io)).collect();
+    assert!(ios == vec![(0, Io::In(1)), (0, Io::Out(2)), (0, Io::In(3)), (0, Io::Out(6)),
+                        (1, Io::In(2)), (1, Io::Out(4)), (1, Io::In(3)), (1, Io::Out(6))]);
+    let mut text = Vec::new();
+    transcript.write_to(&mut text).unwrap();
+    assert!(text.starts_with(b"intcode-transcript 1\n0 0 i1\n"));
+    assert!(Transcript::read_from(&text[..]).unwrap() == transcript);
+    assert!(Transcript::read_from(&b"0 0 i1\n"[..]).is_err());
+    assert!(replay(&program, &transcript) == Ok(()));
+
+    // a program that behaves differently is caught where it first goes off script
+    let tripled = parse_input("3,11,1002,11,3,11,4,11,1105,1,0,0");
+    assert!(replay(&tripled, &transcript) ==
+        Err(ReplayError::UnexpectedOutput { machine: 0, index: 1, expected: Some(Io::Out(2)), got: 3 }));
+    let mut short = transcript.clone();
+    short.entries.truncate(3);
+    assert!(replay(&program, &short) ==
+        Err(ReplayError::UnexpectedOutput { machine: 0, index: 3, expected: None, got: 6 }));
+    let mut long = transcript.clone();
+    long.entries.insert(4, Entry { time: 0, machine: 0, io: Io::Out(12) });
+    assert!(replay(&program, &long) ==
+        Err(ReplayError::UnexpectedInput { machine: 0, index: 4, expected: Io::Out(12) }));
+    let halts = parse_input("3,9,1002,9,2,9,4,9,99,0");
+    assert!(replay(&halts, &transcript) ==
+        Err(ReplayError::Unfinished { machine: 0, index: 2, remaining: 2 }));
+}
+
+pub fn run_test_ascii() {
+    // echo each line after a '>' prompt, then 1000 plus the characters read so far
+    let program = Memory::from(assemble("
+    prompt:
+        out #62
+    loop:
+        in [c]
+        out [c]
+        add [n], #1, [n]
+        eq [c], #10, [t]
+        jf [t], #loop
+        add [n], #1000, [t]
+        out [t]
+        jt #1, #prompt
+    c: .data 0
+    n: .data 0
+    t: .data 0
+    ").unwrap());
+    let recorder = Recorder::new();
+    let machine = new_machine_with_io(program, recorder.record(0, VecDeque::new()), ());
+    let mut echo = Ascii::new(machine);
+    let screen = echo.read_until_prompt().unwrap();
+    assert!(screen == Screen { lines: vec![">".to_string()], values: vec![], stop: Event::NeedsInput });
+    echo.send_line("hi");
+    let screen = echo.read_until_prompt().unwrap();
+    assert!(screen.lines == vec!["hi", ">"] && screen.values == vec![1003]);
+    // lines sent ahead are all read, and nothing is sent twice
+    echo.send_line("ok");
+    echo.send_line("go");
+    let screen = echo.read_until_prompt().unwrap();
+    assert!(screen.lines == vec!["ok", ">go", ">"] && screen.values == vec![1006, 1009]);
+    assert!(recorder.transcript().entries.len() == 9);
+
+    echo.machine.limits.steps = Some(echo.machine.steps + 3);
+    echo.send_line("x");
+    let screen = echo.read_until_prompt().unwrap();
+    assert!(screen.lines == vec!["x"] && screen.stop == Event::Limit(Limit::Steps));
+}
+
+pub fn run_test_script() {
+    let m = Pattern::new("a(b|c)+d").unwrap().find("xxabcbd abd").unwrap();
+    assert!(m.groups == vec![Some((2, 7)), Some((5, 6))]);
+    let words: Vec<String> = Pattern::new(r"[^,\s]+").unwrap().find_all("mug, food ration")
+        .iter().map(|m| m.group("mug, food ration", 0).unwrap()).collect();
+    assert!(words == vec!["mug", "food", "ration"]);
+    assert!(Pattern::new("(a").is_err() && Pattern::new("*").is_err());
+    // alternatives are tried in order, quantifiers are greedy, and the last repetition is captured
+    let m = Pattern::new("(a|ab)(c|bcd)(x?)").unwrap().find("abcd").unwrap();
+    assert!(m.groups == vec![Some((0, 4)), Some((0, 1)), Some((1, 4)), Some((4, 4))]);
+    let m = Pattern::new("(a*)*b|(c)").unwrap().find("xaab").unwrap();
+    assert!(m.groups == vec![Some((1, 4)), Some((1, 3)), None]);
+    assert!(Pattern::new("x*").unwrap().find("abc").unwrap().groups == vec![Some((0, 0))]);
+    // a long run of output that never matches does not exhaust the stack
+    let long = "a".repeat(200_000);
+    assert!(Pattern::new("a*b").unwrap().find(&long).is_none());
+    assert!(Pattern::new("(a|b)+$").unwrap().find(&long).is_none());
+    assert!(Pattern::new("a+").unwrap().find(&long).unwrap().end() == long.len());
+
+    // the echo program from run_test_ascii
+    let program = Memory::from(assemble("
+    prompt:
+        out #62
+    loop:
+        in [c]
+        out [c]
+        add [n], #1, [n]
+        eq [c], #10, [t]
+        jf [t], #loop
+        add [n], #1000, [t]
+        out [t]
+        jt #1, #prompt
+    c: .data 0
+    n: .data 0
+    t: .data 0
+    ").unwrap());
+    let script = Script::parse(r#"
+        expect ">"
+        set word "hi"
+        repeat 2 { send $word ; expect /(\d+)\n/ capture count }   # 1000 + characters read
+        print "read ${count}"
+        for x in "a\nb\n\nc" {
+            send "x=$x"
+            expect /x=(\w)/ capture last
+        }
+        for item in "- mug\n- food ration\n" /- (.*)/ { print "take $item" }
+    "#).unwrap();
+    let run = script.run(&mut new_machine(program.clone()));
+    assert!(run.result == Ok(()));
+    assert!(run.printed == vec!["read 1006", "take mug", "take food ration"]);
+    assert!(run.vars["last"] == "c" && run.vars["x"] == "c");
+    assert!(run.output.starts_with(">hi\n1003\n>hi\n1006\n>x=a\n"));
+
+    let run = Script::parse("expect \">\"\nsend \"q\"\nexpect \"bye\"").unwrap().run(&mut new_machine(program.clone()));
+    let failure = run.result.unwrap_err();
+    assert!(failure.line == 3);
+    assert!(failure.message == "expected \"bye\" but the program is waiting for input, after printing:\nq\n1002\n>");
+    let run = Script::parse("print $nope").unwrap().run(&mut new_machine(program));
+    assert!(run.result.unwrap_err().message == "variable 'nope' is not set");
+    let run = Script::parse("timeout 50 ; expect \"x\"").unwrap().run(&mut new_machine(parse_input("1105,1,0")));
+    assert!(run.result.unwrap_err().message == "expected \"x\" but timed out after 50 ms, with no new output");
+
+    let error = Script::parse("expect \"x\" capture\n").unwrap_err();
+    assert!((error.line, error.col, error.message.as_str()) == (1, 19, "expected a variable name"));
+    let error = Script::parse("repeat 2 {\n  jump\n}").unwrap_err();
+    assert!((error.line, error.col, error.message.as_str()) == (2, 3, "unknown command 'jump'"));
+    assert!(Script::parse("repeat 2 { send 1").unwrap_err().message == "missing '}'");
+    assert!(Script::parse("expect /a(/").unwrap_err().message == "bad pattern: unclosed '('");
+}
+
+pub fn run_test_frames() {
+    // for each input n write (n, 2n, 3n), stopping after 2n if n is 0
+    let program = Memory::from(assemble("
+    loop:
+        in [n]
+        out [n]
+        mul [n], #2, [t]
+        out [t]
+        eq [n], #0, [t]
+        jt [t], #cut
+        mul [n], #3, [t]
+        out [t]
+        jt #1, #loop
+    cut:
+        hlt
+    n: .data 0
+    t: .data 0
+    ").unwrap());
+    let mut m = new_machine(program);
+    let mut tiles = Frames::tiles();
+    assert!(tiles.next_frame(&mut m) == Ok(Event::NeedsInput));
+    m.push_input(1);
+    m.push_input(2);
+    assert!(tiles.next_frame(&mut m) == Ok(Event::Output(Tile { x: 1, y: 2, id: 3 })));
+    assert!(tiles.until_stop(&mut m) == Ok((vec![Tile { x: 2, y: 4, id: 6 }], Event::NeedsInput)));
+    m.push_input(0);
+    let error = tiles.next_frame(&mut m).unwrap_err();
+    assert!(error == FrameError::Truncated { arity: 3, values: vec![0, 0], stop: Some(Event::Halted) });
+    assert!(error.to_string() == "frame cut short after 2 of 3 values [0, 0]: the machine halted");
+
+    let mut sums = Frames::new(2, |v: &[i128]| v[0] + v[1]);
+    assert!(sums.arity() == 2);
+    let (sender, receiver) = mpsc::channel();
+    for v in &[1, 2, 3, 4, 5] {
+        sender.send(*v).unwrap();
+    }
+    drop(sender);
+    let mut values = receiver.iter();
+    assert!(sums.read(&mut values) == Ok(Some(3)) && sums.read(&mut values) == Ok(Some(7)));
+    assert!(sums.read(&mut values) == Err(FrameError::Truncated { arity: 2, values: vec![5], stop: None }));
+    assert!(sums.read(&mut values) == Ok(None));
+    let mut packets = Frames::packets();
+    assert!(packets.read(&mut vec![255, 7, 8].into_iter()) == Ok(Some(Packet { dest: 255, x: 7, y: 8 })));
+    assert!(Frames::paint().read(&mut vec![1, 0].into_iter()) == Ok(Some(Paint { color: 1, turn: 0 })));
+    assert!(Tile { x: -1, y: 0, id: 12 }.is_score());
+}
+
+/// Runs `program` with `input` to a halt, giving the output, steps and final memory.
+fn run_self_modifying(program: &str, input: &[i128]) -> (Vec<i128>, u64, Vec<i128>) {
+    let size = program.split(',').count();
+    let mut m = new_machine_with_io(parse_input(program), input.iter().cloned().collect::<VecDeque<i128>>(), Vec::new());
+    assert!(m.run() == Ok(Event::Halted));
+    let ram = (0..size as i128).map(|address| m.ram.get(address)).collect();
+    (m.output, m.steps, ram)
+}
+
+pub fn run_test_self_modifying() {
+    // an add that turns its own opcode into mul still moves on to the hlt
+    assert!(run_self_modifying("1101,1,1,0,99", &[]) == (vec![], 2, vec![2, 1, 1, 0, 99]));
+    // the same for an input, which then outputs the new opcode
+    assert!(run_self_modifying("3,0,4,0,99", &[104]) == (vec![104], 3, vec![104, 0, 4, 0, 99]));
+    // operands are read before the write, even one into the instruction's own operand
+    assert!(run_self_modifying("1,0,0,3,99", &[]) == (vec![], 2, vec![1, 0, 0, 2, 99]));
+    assert!(run_self_modifying("1107,5,1,1,99", &[]) == (vec![], 2, vec![1107, 0, 1, 1, 99]));
+    // a write into the next instruction takes effect when it is fetched
+    assert!(run_self_modifying("1,1,1,4,99,5,6,0,99", &[]) == (vec![], 3, vec![30, 1, 1, 4, 2, 5, 6, 0, 99]));
+    // a loop whose jt condition is patched into the jt itself on each pass
+    assert!(run_self_modifying("1101,4,0,11,4,11,1008,11,4,11,1105,1,4,99", &[])
+        == (vec![4, 1], 8, vec![1101, 4, 0, 11, 4, 11, 1008, 11, 4, 11, 1105, 0, 4, 99]));
+
+    let program = parse_input("1101,0,0,100,1101,1,1,1,1101,5,5,9,1101,7,0,20,99,0,0,0,0");
+    let mut m = new_machine(program);
+    m.start_code_watch();
+    assert!(m.run() == Ok(Event::Halted));
+    let watch = m.take_code_watch().unwrap();
+    assert!(watch.patches == vec![
+        Patch { step: 1, by: 4, address: 1, instruction: 0, old: 0, new: 2 },
+        Patch { step: 2, by: 8, address: 9, instruction: 8, old: 5, new: 10 },
+    ]);
+    assert!(watch.patched_instructions() == vec![0, 8]);
+    assert!(watch.is_executed(16) && !watch.is_executed(20));
+    assert!(watch.patches[0].to_string() == "step 1: 4 wrote 2 to [1] in the instruction at 0, was 0");
+    // code patched before it runs is not reported, only the mul overwriting the add after it ran
+    let mut m = new_machine(parse_input("1,1,1,4,99,5,6,0,99"));
+    m.start_code_watch();
+    m.start_profile();
+    assert!(m.run() == Ok(Event::Halted));
+    assert!(m.take_code_watch().unwrap().patches
+        == vec![Patch { step: 1, by: 4, address: 0, instruction: 0, old: 1, new: 30 }]);
+    assert!(m.take_profile().unwrap().total == 3);
+    // watching does not change how a program faults
+    let mut m = new_machine(parse_input("1105,1,-3"));
+    m.start_code_watch();
+    assert!(m.run() == Err(IntcodeError::NegativeAddress { ip: -3, opcode: 0, address: -3 }));
+    let mut m = new_machine(parse_input("109,-1,21101,1,1,0,99"));
+    m.start_code_watch();
+    assert!(m.run() == Err(IntcodeError::NegativeAddress { ip: 2, opcode: 21101, address: -1 }));
+    assert!(m.take_code_watch().unwrap().patches.is_empty());
+}
+
+pub fn run_test_strict() {
+    assert!(check_instr(0, 1002) == Ok(()) && check_instr(0, 21101) == Ok(()) && check_instr(0, 99) == Ok(()));
+    assert!(check_instr(4, 11101) == Err(IntcodeError::ImmediateWrite { ip: 4, opcode: 11101 }));
+    assert!(check_instr(4, 103) == Err(IntcodeError::ImmediateWrite { ip: 4, opcode: 103 }));
+    assert!(check_instr(4, 1104) == Err(IntcodeError::ExtraDigits { ip: 4, opcode: 1104 }));
+    assert!(check_instr(4, 100099) == Err(IntcodeError::ExtraDigits { ip: 4, opcode: 100099 }));
+    assert!(check_instr(4, 3004) == Err(IntcodeError::ExtraDigits { ip: 4, opcode: 3004 }));
+    assert!(check_instr(4, 305) == Err(IntcodeError::BadMode { ip: 4, opcode: 305, mode: 3 }));
+    assert!(check_instr(4, 42) == Err(IntcodeError::BadOpcode { ip: 4, opcode: 42 }));
+    assert!(check_instr(4, 0) == Err(IntcodeError::BadOpcode { ip: 4, opcode: 0 }));
+
+    // an immediate write runs as positional unless the machine is strict
+    let program = parse_input("11101,2,3,5,99,0");
+    let mut m = new_machine(program.clone());
+    assert!(m.run() == Ok(Event::Halted) && m.ram.get(5) == 5);
+    let mut m = new_machine(program);
+    m.strict = true;
+    let error = m.run().unwrap_err();
+    assert!(error.to_string() == "immediate mode write in 11101 at ip 0");
+    assert!(m.ip == 0 && m.ram.get(5) == 0 && m.steps == 0);
+    let mut m = new_machine(parse_input("104,7,100099"));
+    m.strict = true;
+    assert!(m.run_until_event() == Ok(Event::Output(7)));
+    assert!(m.run() == Err(IntcodeError::ExtraDigits { ip: 2, opcode: 100099 }));
+
+    // the data word at 3 is jumped over, the immediate write at 4 and extra digit at 8 are
+    // reached in turn and the bad mode at 14 only through the jf
+    let program = parse_input("1105,1,4,7777,11101,1,2,5,10104,9,1006,9,14,99,304,1,99");
+    assert!(preflight(&program) == vec![
+        IntcodeError::ImmediateWrite { ip: 4, opcode: 11101 },
+        IntcodeError::ExtraDigits { ip: 8, opcode: 10104 },
+        IntcodeError::BadMode { ip: 14, opcode: 304, mode: 3 },
+    ]);
+    assert!(preflight(&parse_input("3,9,1002,9,2,9,4,9,99,0")).is_empty());
+}
+
+pub fn run_test_cfg() {
+    let program = Memory::from(assemble("
+        arb #10
+        add #back, #0, rb+0
+        jt #1, #double
+    back:
+        in [x]
+        jf [x], #done
+        jt [x], [x]
+    done:
+        add #fin, #0, rb+0
+        jt #1, [ptr]
+    fin:
+        hlt
+    double:
+        arb #2
+        mul rb-1, #2, rb-1
+        arb #-2
+        jt #1, rb+0
+    x: .data 0
+    ptr: .data double
+    ").unwrap());
+    let cfg = Cfg::build(&program);
+    let exits: Vec<(i128, Vec<Exit>)> = cfg.blocks.values().map(|b| (b.start, b.exits.clone())).collect();
+    assert!(exits == vec![
+        (0, vec![Exit::Call { target: 25, returns: 9 }]),
+        (9, vec![Exit::Jump(17), Exit::Fall(14)]),
+        (14, vec![Exit::Computed, Exit::Fall(17)]),
+        (17, vec![Exit::ComputedCall { returns: 24 }]),
+        (24, vec![Exit::Halt]),
+        (25, vec![Exit::Return]),
+    ]);
+    assert!(cfg.blocks[&0].instructions.len() == 3 && cfg.blocks[&25].end() == 36);
+    assert!(cfg.blocks[&9].successors() == vec![17, 14] && cfg.blocks[&0].successors() == vec![25, 9]);
+    assert!(cfg.functions.len() == 1 && cfg.functions[&25].iter().eq(&[25]));
+    assert!(cfg.unresolved() == vec![14, 21]);
+    assert!(cfg.block_at(29).unwrap().start == 25 && cfg.block_at(36).is_none());
+
+    let dot = cfg.to_dot();
+    assert!(dot.starts_with("digraph intcode {\n"));
+    assert!(dot.contains("    subgraph cluster_f25 {\n        label=\"f25\";\n        b25 [label=\"25: arb #2\\l"));
+    assert!(dot.contains("33: jt #1, rb+0\\l(return)\\l\"];"));
+    assert!(dot.contains("    b0 -> b25 [label=\"call\"];\n    b0 -> b9 [style=dashed];\n"));
+    assert!(dot.contains("    b9 -> b17 [label=\"then\"];\n    b9 -> b14 [label=\"else\"];\n"));
+    assert!(dot.contains("(computed jump)\\l\" color=red];"));
+    assert!(dot.contains("21: jt #1, [37]\\l(computed call)\\l\" color=red];\n"));
+    assert!(dot.contains("    b17 -> b24 [style=dashed];\n"));
+
+    // pushed constants that overflow must not panic the analysis
+    let program = parse_input(&format!("21101,{},1,0,1105,1,8,99,99", i128::MAX));
+    let exits: Vec<(i128, Vec<Exit>)> = Cfg::build(&program).blocks.values().map(|b| (b.start, b.exits.clone())).collect();
+    assert!(exits == vec![(0, vec![Exit::Jump(8)]), (8, vec![Exit::Halt])]);
+    decompile(&program);
+}
+
+pub fn run_test_decompile() {
+    let program = Memory::from(assemble("
+        arb #50
+        in [n]
+    head:
+        lt #0, [n], [c]
+        jf [c], #end
+        add [n], #0, rb+1
+        add #back, #0, rb+0
+        jt #1, #square
+    back:
+        add [sum], rb+1, [sum]
+        add [n], #-1, [n]
+        jt #1, #head
+    end:
+        eq [sum], #0, [c]
+        jt [c], #zero
+        out [sum]
+        hlt
+    zero:
+        out #-1
+        hlt
+    square:
+        arb #3
+        mul rb-2, rb-2, rb-2
+        lt #100, rb-2, rb-1
+        jf rb-1, #small
+        add #100, #0, rb-2
+    small:
+        arb #-3
+        jt #1, rb+0
+    n: .data 0
+    c: .data 0
+    sum: .data 0
+    ").unwrap());
+    assert!(decompile(&program) == "\
+fn main() {
+    m68 = input()
+    while 0 < m68 {
+        t1 = f46(m68)
+        m70 = m70 + t1
+        m68 = m68 - 1
+    }
+    if m70 == 0 {
+        output(-1)
+        halt
+    }
+    output(m70)
+    halt
+}
+
+fn f46(a1) {
+    a1 = a1 * a1
+    if 100 < a1 {
+        a1 = 100
+    }
+    return a1
+}
+");
+
+    let patching = Memory::from(assemble("
+        add [x], #1, [6]
+        jt #1, #0
+    x: .data 4
+    ").unwrap());
+    assert!(decompile(&patching) == "fn main() {\n    loop {\n        m6 = m7 + 1  // patches the instruction at 4\n    }\n}\n");
+}
+
+pub fn run_test_transpile() {
+    let program = parse_input(include_str!("../input19.txt").trim());
+    let source = transpile(&program, "aoc2019::intcode");
+    assert!(source.contains("\nuse aoc2019::intcode::{Divergence, Event, Input, IntcodeError, Limits, Machine,"));
+    assert!(source.contains(&format!("\npub const IMAGE: [i128; {}] = [\n", program.image_len())));
+    // every straight line segment the cfg finds gets an arm, guarded by its words being intact
+    assert!(source.contains("\n            0 if intact(m, 0, 4) => {\n                // 0: arb #424\n"));
+    assert!(source.contains("\n            _ => {\n                if let Some(event) = run_one_step(m)? {\n"));
+    // running it is checked in day 19 itself, which compiles it from src/day19_beam.rs
+
+    // the interpreter always agrees with itself, and a run that does not is caught
+    for &(x, y) in &[(0, 0), (877, 1057), (976, 1057)] {
+        let mut m = new_machine(program.clone());
+        m.push_input(x);
+        m.push_input(y);
+        let expected = vec![Event::Output(m.next_output().unwrap().unwrap()), Event::Halted];
+        assert!(differential(&program, |m| m.run_until_event(), &[x, y]) == Ok(expected));
+    }
+    let off_by_one = |m: &mut Machine| match m.run_until_event() {
+        Ok(Event::Output(value)) => Ok(Event::Output(value + 1)),
+        result => result,
+    };
+    let divergence = differential(&program, off_by_one, &[0, 0]).unwrap_err();
+    assert!(divergence.agreed.is_empty());
+    assert!(divergence.to_string().starts_with("after 0 events the interpreter gave Output(1) at ip"));
+    assert!(divergence.compiled.starts_with("Output(2) at ip"));
+    // so is one that faults differently, or not at all
+    let overflowing = parse_input(&format!("109,{},204,1,99", i128::MAX));
+    assert!(differential(&overflowing, |m| m.run_until_event(), &[]) == Ok(vec![]));
+    let divergence = differential(&overflowing, |_| Ok(Event::Halted), &[]).unwrap_err();
+    assert!(divergence.interpreted.starts_with("overflow in 204 at ip 2") && divergence.compiled.starts_with("Halted"));
+
+    // address arithmetic that overflows is left to the interpreter, which faults on it
+    let source = transpile(&overflowing, "aoc2019::intcode");
+    assert!(source.contains("m.base = match m.base.checked_add(a) {\n                    Some(base) => base,\n                    None => { m.ip = 0; if let Some(event) = run_one_step(m)? { return Ok(event); } continue; }\n"));
+    assert!(source.contains("let at_a = match m.base.checked_add(1) {") && !source.contains("m.base +"));
+}
+
+pub fn run_tests() {
+    assert!(parse_instr(1105) == (5, 1, 1, 0));
+    assert!(parse_instr(1008) == (8, 0, 1, 0));
+    assert!(parse_instr(209) == (9, 2, 0, 0));
+    test_machine("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", 0, 0);
+    println!("Test 1 successful");
+    test_machine("3,3,1105,-1,9,1101,0,0,12,4,12,99,1", 1, 1);
+    println!("Test 2 successful");
+    test_machine(
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        1, 999);
+    println!("Test 3 successful");
+    test_machine(
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        8, 1000);
+    println!("Test 4 successful");
+    test_machine(
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        1000, 1001);
+    println!("Test 5 successful");
+    test_machine(
+        "104,-1125899906842624,99",
+        0, -1125899906842624
+    );
+    println!("Test 6 successful");
+    test_machine("109,1,204,-1,99", 0, 109);
+    run_test_quine();
+    println!("Test 7 successful");
+    run_test_big();
+    println!("Test 8 successful");
+    run_test_events();
+    println!("Test 9 successful");
+    run_test_threaded();
+    println!("Test 10 successful");
+    run_test_devices();
+    println!("Test 11 successful");
+    run_test_errors();
+    println!("Test 12 successful");
+    run_test_memory();
+    println!("Test 13 successful");
+    run_test_snapshot();
+    println!("Test 14 successful");
+    run_test_fork();
+    println!("Test 15 successful");
+    run_test_disasm();
+    println!("Test 16 successful");
+    run_test_asm();
+    println!("Test 17 successful");
+    run_test_debugger();
+    println!("Test 18 successful");
+    run_test_trace();
+    println!("Test 19 successful");
+    run_test_reverse();
+    println!("Test 20 successful");
+    run_test_profile();
+    println!("Test 21 successful");
+    run_test_coverage();
+    println!("Test 22 successful");
+    run_test_words();
+    println!("Test 23 successful");
+    run_test_limits();
+    println!("Test 24 successful");
+    run_test_transcript();
+    println!("Test 25 successful");
+    run_test_ascii();
+    println!("Test 26 successful");
+    run_test_script();
+    println!("Test 27 successful");
+    run_test_frames();
+    println!("Test 28 successful");
+    run_test_self_modifying();
+    println!("Test 29 successful");
+    run_test_strict();
+    println!("Test 30 successful");
+    run_test_cfg();
+    println!("Test 31 successful");
+    run_test_decompile();
+    println!("Test 32 successful");
+    run_test_transpile();
+    println!("Test 33 successful");
+    run_test_debugger_addresses();
+    println!("Test 34 successful");
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use std::iter;
+
+use super::{Event, Input, IntcodeError, Machine, Output};
+
+/// Everything a program printed before it stopped to wait for input, or halted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    /// Text output split at newlines, which are dropped. Text after the last newline,
+    /// like a prompt that does not end its line, is the last line.
+    pub lines: Vec<String>,
+    /// Output values outside ASCII, like the puzzle answers, in the order they came.
+    pub values: Vec<i128>,
+    /// `NeedsInput` at a prompt, otherwise `Halted` or `Limit`.
+    pub stop: Event,
+}
+
+/// Talks to a program that reads and writes ASCII text, one line at a time. Output
+/// values from 0 to 127 are text and anything else is reported on its own.
+///
+/// ```ignore
+/// let mut droid = Ascii::new(new_machine(program));
+/// droid.read_until_prompt()?;
+/// droid.send_line("WALK");
+/// let screen = droid.read_until_prompt()?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Ascii<I = VecDeque<i128>, O = ()> {
+    pub machine: Machine<I, O>,
+}
+
+impl<I: Input + Extend<i128>, O: Output> Ascii<I, O> {
+    pub fn new(machine: Machine<I, O>) -> Ascii<I, O> {
+        Ascii { machine }
+    }
+
+    /// Queues `line` and a newline as input.
+    pub fn send_line(&mut self, line: &str) {
+        self.machine.input.extend(line.chars().map(|c| c as i128).chain(iter::once('\n' as i128)));
+    }
+
+    /// Runs until the program wants input it has not been sent, or stops.
+    pub fn read_until_prompt(&mut self) -> Result<Screen, IntcodeError> {
+        let mut lines = Vec::new();
+        let mut values = Vec::new();
+        let mut line = String::new();
+        loop {
+            match self.machine.run_until_event()? {
+                Event::Output(10) => lines.push(line.split_off(0)),
+                Event::Output(x) if (0..128).contains(&x) => line.push(x as u8 as char),
+                Event::Output(x) => values.push(x),
+                stop => {
+                    if !line.is_empty() {
+                        lines.push(line);
+                    }
+                    return Ok(Screen { lines, values, stop });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::disasm::opcode_info;
+
+/// An assembly error at `line` and `col`, both counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+fn error<T>(line: usize, col: usize, message: String) -> Result<T, AsmError> {
+    Err(AsmError { line, col, message })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A mnemonic, directive, label or `rb`.
+    Word(String),
+    Number(i128),
+    Str(String),
+    Punct(char),
+}
+
+/// Splits a line into tokens paired with their column, dropping any `;` comment.
+fn tokenize(text: &str, line: usize) -> Result<Vec<(Token, usize)>, AsmError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let col = i + 1;
+        if c == ';' {
+            break;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            match word.parse() {
+                Ok(n) => tokens.push((Token::Number(n), col)),
+                Err(_) => return error(line, col, format!("bad number '{}'", word)),
+            }
+        } else if c.is_alphabetic() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push((Token::Word(chars[start..i].iter().collect()), col));
+        } else if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return error(line, col, "unterminated string".to_string()),
+                    Some('"') => break,
+                    Some('\\') => {
+                        let escaped = match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('0') => '\0',
+                            Some('\\') => '\\',
+                            Some('"') => '"',
+                            _ => return error(line, i + 1, "unknown escape".to_string()),
+                        };
+                        s.push(escaped);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        s.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Str(s), col));
+        } else if ":,[]#+-".contains(c) {
+            tokens.push((Token::Punct(c), col));
+            i += 1;
+        } else {
+            return error(line, col, format!("unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// A word whose value may depend on labels, as a sum of signed terms.
+#[derive(Debug, Clone)]
+struct Expr {
+    constant: i128,
+    /// Labels to add (or subtract, if the flag is false), with the column they were used at.
+    labels: Vec<(String, bool, usize)>,
+}
+
+impl Expr {
+    fn constant(value: i128) -> Expr {
+        Expr { constant: value, labels: Vec::new() }
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    line: usize,
+    /// Column just past the end of the line, for errors about missing tokens.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    fn col(&self) -> usize {
+        self.tokens.get(self.pos).map(|t| t.1).unwrap_or(self.end)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|t| t.0.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn fail<T>(&self, message: &str) -> Result<T, AsmError> {
+        let found = match self.peek() {
+            None => "end of line".to_string(),
+            Some(Token::Word(w)) => format!("'{}'", w),
+            Some(Token::Number(n)) => format!("'{}'", n),
+            Some(Token::Str(_)) => "a string".to_string(),
+            Some(Token::Punct(c)) => format!("'{}'", c),
+        };
+        error(self.line, self.col(), format!("expected {}, found {}", message, found))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `term (('+' | '-') term)*`, where a term is a number or a label and the
+    /// first may be negated. With `start` the first term is already given and
+    /// the expression continues from its sign.
+    fn expr(&mut self, start: Option<i128>) -> Result<Expr, AsmError> {
+        let mut expr = Expr::constant(start.unwrap_or(0));
+        let mut positive = true;
+        if start.is_some() {
+            if self.eat('-') {
+                positive = false;
+            } else if !self.eat('+') {
+                return Ok(expr);
+            }
+        } else if self.eat('-') {
+            positive = false;
+        }
+        loop {
+            let col = self.col();
+            match self.peek().cloned() {
+                Some(Token::Number(n)) => expr.constant += if positive { n } else { -n },
+                Some(Token::Word(ref w)) if w != "rb" && !w.starts_with('.') =>
+                    expr.labels.push((w.clone(), positive, col)),
+                _ => return self.fail("a number or label"),
+            }
+            self.pos += 1;
+            if self.eat('+') {
+                positive = true;
+            } else if self.eat('-') {
+                positive = false;
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// An operand and its mode: `[expr]`, `#expr` or `rb`, `rb+expr`, `rb-expr`.
+    fn operand(&mut self) -> Result<(i128, Expr), AsmError> {
+        if self.eat('[') {
+            let expr = self.expr(None)?;
+            if !self.eat(']') {
+                return self.fail("']'");
+            }
+            Ok((0, expr))
+        } else if self.eat('#') {
+            Ok((1, self.expr(None)?))
+        } else if self.peek() == Some(&Token::Word("rb".to_string())) {
+            self.pos += 1;
+            Ok((2, self.expr(Some(0))?))
+        } else {
+            self.fail("an operand ('[a]', '#v' or 'rb+o')")
+        }
+    }
+
+    fn end_of_line(&self) -> Result<(), AsmError> {
+        if self.done() { Ok(()) } else { self.fail("end of line") }
+    }
+}
+
+fn opcode_for(mnemonic: &str) -> Option<(i128, usize)> {
+    (1..100).find_map(|opcode| match opcode_info(opcode) {
+        Some((name, count)) if name == mnemonic => Some((opcode, count)),
+        _ => None,
+    })
+}
+
+/// Assembles source in the disassembler's syntax into program words.
+///
+/// Each line holds any number of `label:` definitions followed by an instruction
+/// (`add #5, [x], rb-1`), `.data` with a comma separated list of values, or
+/// `.string "text"`, which emits one word per character. Anything after `;` is a
+/// comment. Values are numbers, labels or sums of them like `end-start+1`.
+pub fn assemble(source: &str) -> Result<Vec<i128>, AsmError> {
+    let mut labels: HashMap<String, i128> = HashMap::new();
+    // (line, words) for every line that emits anything, resolved once all labels are known
+    let mut emitted: Vec<(usize, Vec<Expr>)> = Vec::new();
+    let mut address = 0;
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let tokens = tokenize(text, line)?;
+        let end = text.chars().count() + 1;
+        let mut p = Parser { tokens, pos: 0, line, end };
+
+        while let (Some(Token::Word(name)), Some(&(Token::Punct(':'), _))) =
+            (p.peek().cloned(), p.tokens.get(p.pos + 1))
+        {
+            if name == "rb" || name.starts_with('.') {
+                return error(line, p.col(), format!("'{}' cannot be used as a label", name));
+            }
+            if labels.insert(name.clone(), address).is_some() {
+                return error(line, p.col(), format!("label '{}' is defined twice", name));
+            }
+            p.pos += 2;
+        }
+        if p.done() {
+            continue;
+        }
+
+        let col = p.col();
+        let name = match p.next() {
+            Some(Token::Word(name)) => name,
+            _ => {
+                p.pos -= 1;
+                return p.fail("a mnemonic or directive");
+            }
+        };
+        let mut words = Vec::new();
+        match name.as_str() {
+            ".data" => loop {
+                words.push(p.expr(None)?);
+                if !p.eat(',') {
+                    break;
+                }
+            },
+            ".string" => match p.next() {
+                Some(Token::Str(s)) => words.extend(s.chars().map(|c| Expr::constant(c as i128))),
+                _ => {
+                    p.pos -= 1;
+                    return p.fail("a string");
+                }
+            },
+            _ => {
+                let (opcode, count) = match opcode_for(&name) {
+                    Some(info) => info,
+                    None => return error(line, col, format!("unknown mnemonic '{}'", name)),
+                };
+                let mut instr = opcode;
+                words.push(Expr::constant(0));
+                for k in 0..count {
+                    if k > 0 && !p.eat(',') {
+                        return p.fail("','");
+                    }
+                    let (mode, expr) = p.operand()?;
+                    instr += mode * 10i128.pow(k as u32 + 2);
+                    words.push(expr);
+                }
+                words[0] = Expr::constant(instr);
+            }
+        }
+        p.end_of_line()?;
+        address += words.len() as i128;
+        emitted.push((line, words));
+    }
+
+    let mut program = Vec::new();
+    for (line, words) in emitted {
+        for expr in words {
+            let mut value = expr.constant;
+            for (name, positive, col) in expr.labels {
+                match labels.get(&name) {
+                    Some(&at) => value += if positive { at } else { -at },
+                    None => return error(line, col, format!("undefined label '{}'", name)),
+                }
+            }
+            program.push(value);
+        }
+    }
+    Ok(program)
+}
+
+/// Assembles to the comma separated text that `parse_input` reads.
+pub fn assemble_to_string(source: &str) -> Result<String, AsmError> {
+    let words: Vec<String> = assemble(source)?.iter().map(|w| w.to_string()).collect();
+    Ok(words.join(","))
+}
@@ -0,0 +1,253 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::disasm::{Instruction, Operand, decode, reachable};
+use super::Memory;
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// On to the next block, by falling through or when a branch is not taken.
+    Fall(i128),
+    /// A jump with an immediate target, taken always or when its condition holds.
+    Jump(i128),
+    /// The function idiom: `returns` is pushed relative to the base and then
+    /// control jumps to `target`, which comes back to `returns`.
+    Call { target: i128, returns: i128 },
+    /// A call through memory, like a function pointer, that comes back to `returns`.
+    ComputedCall { returns: i128 },
+    /// An unconditional jump through a cell relative to the base, which is how a
+    /// function goes back to the address its caller pushed.
+    Return,
+    /// A jump through memory that cannot be followed without running the program.
+    Computed,
+    Halt,
+}
+
+/// Straight line code: only the first instruction is jumped to and only the last one jumps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: i128,
+    pub instructions: Vec<Instruction>,
+    pub exits: Vec<Exit>,
+}
+
+impl Block {
+    /// The address just past the block.
+    pub fn end(&self) -> i128 {
+        self.instructions.last().map_or(self.start, |i| i.address + i.size() as i128)
+    }
+
+    /// Whether the block ends in a jump or call through memory other than a return.
+    pub fn is_unresolved(&self) -> bool {
+        self.exits.iter().any(|exit| matches!(exit, Exit::Computed | Exit::ComputedCall { .. }))
+    }
+
+    /// Blocks control can go to next, calls included.
+    pub fn successors(&self) -> Vec<i128> {
+        let mut next = Vec::new();
+        for exit in &self.exits {
+            match *exit {
+                Exit::Fall(to) | Exit::Jump(to) | Exit::ComputedCall { returns: to } => next.push(to),
+                Exit::Call { target, returns } => next.extend(&[target, returns]),
+                _ => {}
+            }
+        }
+        next
+    }
+}
+
+/// The control flow graph of a loaded program, over the code `disasm::reachable` finds.
+///
+/// ```ignore
+/// let cfg = Cfg::build(&program);
+/// fs::write("program.dot", cfg.to_dot())?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: BTreeMap<i128, Block>,
+    /// For each call target, the blocks reached from it without following calls.
+    pub functions: BTreeMap<i128, BTreeSet<i128>>,
+}
+
+/// Whether a jump is taken, if its condition is an immediate.
+fn taken(instr: &Instruction) -> Option<bool> {
+    match instr.operands[0] {
+        Operand::Immediate(x) => Some((x != 0) == (instr.opcode == 5)),
+        _ => None,
+    }
+}
+
+/// The return address an `add` or `mul` of two immediates pushes relative to the base.
+/// It wraps like an unchecked machine would, so odd constants cannot panic the analysis.
+pub(super) fn pushed(instr: &Instruction) -> Option<i128> {
+    match (instr.opcode, &instr.operands[..]) {
+        (1, [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)]) => Some(a.wrapping_add(*b)),
+        (2, [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)]) => Some(a.wrapping_mul(*b)),
+        _ => None,
+    }
+}
+
+fn exits(instructions: &[Instruction], next: i128) -> Vec<Exit> {
+    let last = instructions.last().unwrap();
+    match last.opcode {
+        99 => vec![Exit::Halt],
+        5 | 6 => {
+            let taken = taken(last);
+            let call = taken == Some(true) && instructions.iter().any(|i| pushed(i) == Some(next));
+            let mut exits = Vec::new();
+            if taken != Some(false) {
+                exits.push(match last.operands[1] {
+                    Operand::Immediate(target) if call => Exit::Call { target, returns: next },
+                    Operand::Immediate(target) => Exit::Jump(target),
+                    _ if call => Exit::ComputedCall { returns: next },
+                    Operand::Relative(_) if taken == Some(true) => Exit::Return,
+                    _ => Exit::Computed,
+                });
+            }
+            if taken != Some(true) {
+                exits.push(Exit::Fall(next));
+            }
+            exits
+        }
+        _ => vec![Exit::Fall(next)],
+    }
+}
+
+impl Cfg {
+    pub fn build(program: &Memory) -> Cfg {
+        let code = reachable(program);
+        let instructions: BTreeMap<i128, Instruction> = code.iter()
+            .filter_map(|&address| decode(program, address).map(|i| (address, i)))
+            .collect();
+
+        let mut leaders: BTreeSet<i128> = BTreeSet::new();
+        leaders.insert(0);
+        for instr in instructions.values() {
+            if instr.opcode == 5 || instr.opcode == 6 || instr.opcode == 99 {
+                leaders.insert(instr.address + instr.size() as i128);
+                if let Some(target) = instr.jump_target() {
+                    leaders.insert(target);
+                }
+            }
+        }
+
+        let mut cfg = Cfg::default();
+        for &start in leaders.iter().filter(|a| instructions.contains_key(a)) {
+            let mut body = Vec::new();
+            let mut address = start;
+            while let Some(instr) = instructions.get(&address) {
+                body.push(instr.clone());
+                address += instr.size() as i128;
+                let ends = instr.opcode == 5 || instr.opcode == 6 || instr.opcode == 99;
+                if ends || leaders.contains(&address) {
+                    break;
+                }
+            }
+            let exits = exits(&body, address);
+            cfg.blocks.insert(start, Block { start, instructions: body, exits });
+        }
+
+        let entries: BTreeSet<i128> = cfg.blocks.values()
+            .flat_map(|b| b.exits.iter())
+            .filter_map(|exit| match *exit {
+                Exit::Call { target, .. } => Some(target),
+                _ => None,
+            })
+            .collect();
+        for entry in entries {
+            let body = cfg.body(entry);
+            cfg.functions.insert(entry, body);
+        }
+        cfg
+    }
+
+    /// Blocks reached from `entry`, stepping over calls to where they return.
+    pub(super) fn body(&self, entry: i128) -> BTreeSet<i128> {
+        let mut seen = BTreeSet::new();
+        let mut work = vec![entry];
+        while let Some(start) = work.pop() {
+            let block = match self.blocks.get(&start) {
+                Some(block) if seen.insert(start) => block,
+                _ => continue,
+            };
+            for exit in &block.exits {
+                match *exit {
+                    Exit::Fall(to) | Exit::Jump(to) | Exit::Call { returns: to, .. } | Exit::ComputedCall { returns: to } =>
+                        work.push(to),
+                    _ => {}
+                }
+            }
+        }
+        seen
+    }
+
+    /// The block holding the instruction at `address`.
+    pub fn block_at(&self, address: i128) -> Option<&Block> {
+        let (_, block) = self.blocks.range(..=address).next_back()?;
+        if address < block.end() { Some(block) } else { None }
+    }
+
+    /// Addresses of the jumps and calls through memory that are not function returns.
+    pub fn unresolved(&self) -> Vec<i128> {
+        self.blocks.values()
+            .filter(|b| b.is_unresolved())
+            .map(|b| b.instructions.last().unwrap().address)
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT, one box per block listing its instructions. Functions are
+    /// drawn as clusters, calls as edges to the function and a dashed edge to where it
+    /// returns, and blocks ending in a jump or call through memory are red.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph intcode {\n    node [shape=box fontname=\"monospace\"];\n");
+        let mut clustered = BTreeSet::new();
+        for (entry, body) in &self.functions {
+            writeln!(out, "    subgraph cluster_f{} {{\n        label=\"f{}\";", entry, entry).unwrap();
+            for start in body.iter().filter(|&&start| clustered.insert(start)) {
+                writeln!(out, "        {}", self.node(&self.blocks[start])).unwrap();
+            }
+            out += "    }\n";
+        }
+        for block in self.blocks.values().filter(|b| !clustered.contains(&b.start)) {
+            writeln!(out, "    {}", self.node(block)).unwrap();
+        }
+        for block in self.blocks.values() {
+            let conditional = block.exits.len() > 1;
+            for exit in &block.exits {
+                let edge = match *exit {
+                    Exit::Fall(to) if conditional => format!("b{} -> b{} [label=\"else\"]", block.start, to),
+                    Exit::Fall(to) => format!("b{} -> b{}", block.start, to),
+                    Exit::Jump(to) if conditional => format!("b{} -> b{} [label=\"then\"]", block.start, to),
+                    Exit::Jump(to) => format!("b{} -> b{}", block.start, to),
+                    Exit::Call { target, returns } => format!(
+                        "b{} -> b{} [label=\"call\"];\n    b{} -> b{} [style=dashed]",
+                        block.start, target, block.start, returns),
+                    Exit::ComputedCall { returns } => format!("b{} -> b{} [style=dashed]", block.start, returns),
+                    _ => continue,
+                };
+                writeln!(out, "    {};", edge).unwrap();
+            }
+        }
+        out += "}\n";
+        out
+    }
+
+    fn node(&self, block: &Block) -> String {
+        let mut label = String::new();
+        for instr in &block.instructions {
+            write!(label, "{}: {}\\l", instr.address, instr).unwrap();
+        }
+        for exit in &block.exits {
+            match exit {
+                Exit::Return => label += "(return)\\l",
+                Exit::ComputedCall { .. } => label += "(computed call)\\l",
+                Exit::Computed => label += "(computed jump)\\l",
+                Exit::Halt => label += "(halt)\\l",
+                _ => {}
+            }
+        }
+        let color = if block.is_unresolved() { " color=red" } else { "" };
+        format!("b{} [label=\"{}\"{}];", block.start, label, color)
+    }
+}
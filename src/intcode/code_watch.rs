@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::disasm::opcode_info;
+use super::word::saturate;
+use super::{Event, Input, IntcodeError, Machine, Output, Word, get_instr, parse_instr, read, run_unwatched,
+    write_address};
+
+/// A write into a cell that had already been run as part of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Patch {
+    /// `Machine::steps` when the write happened.
+    pub step: u64,
+    /// The instruction that wrote.
+    pub by: i128,
+    pub address: i128,
+    /// Where the executed instruction that `address` belongs to starts.
+    pub instruction: i128,
+    pub old: i128,
+    pub new: i128,
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {}: {} wrote {} to [{}] in the instruction at {}, was {}",
+            self.step, self.by, self.new, self.address, self.instruction, self.old)
+    }
+}
+
+/// Every write into executed code while watching was on. Start one with
+/// `Machine::start_code_watch`.
+///
+/// A cell counts as code once an instruction has been fetched over it, operands included,
+/// so an instruction that writes into its own cells is caught too. Writes into code that
+/// has not run yet, like a program filling in an instruction before it gets there, are not.
+/// Words too big for an `i128` are recorded clamped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeWatch {
+    pub patches: Vec<Patch>,
+    /// For each executed cell, where the last instruction fetched over it starts.
+    executed: HashMap<i128, i128>,
+}
+
+impl CodeWatch {
+    /// Whether the cell at `address` has been run as part of an instruction.
+    pub fn is_executed(&self, address: i128) -> bool {
+        self.executed.contains_key(&address)
+    }
+
+    /// Addresses of the instructions that were patched, each once and in order.
+    pub fn patched_instructions(&self) -> Vec<i128> {
+        let mut addresses: Vec<i128> = self.patches.iter().map(|patch| patch.instruction).collect();
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    }
+}
+
+impl<I, O, W> Machine<I, O, W> {
+    /// Watches for writes into executed code from now on, until `take_code_watch`.
+    pub fn start_code_watch(&mut self) {
+        self.code_watch = Some(CodeWatch::default());
+    }
+
+    pub fn take_code_watch(&mut self) -> Option<CodeWatch> {
+        self.code_watch.take()
+    }
+}
+
+/// Runs one instruction, first marking its cells as executed and then noting its write
+/// if it lands in any executed cell.
+pub(super) fn watched_step<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let ip = m.ip;
+    let (opcode, _, _, _) = parse_instr(get_instr(m)?);
+    let count = opcode_info(opcode).map(|(_, count)| count).unwrap_or(0);
+    // a write that cannot happen faults the instruction, which is then not recorded
+    let write = write_address(m).ok().flatten()
+        .and_then(|address| read(m, address).ok().map(|old| (address, saturate(&old))));
+
+    let event = run_unwatched(m)?;
+    if event == Some(Event::NeedsInput) {
+        return Ok(event);
+    }
+    let step = m.steps;
+    let new = write.map(|(address, _)| saturate(&m.ram.get(address)));
+    let watch = match m.code_watch.as_mut() {
+        Some(watch) => watch,
+        None => return Ok(event),
+    };
+    for address in ip..=ip + count as i128 {
+        watch.executed.insert(address, ip);
+    }
+    if let (Some((address, old)), Some(new)) = (write, new) {
+        if let Some(&instruction) = watch.executed.get(&address) {
+            watch.patches.push(Patch { step, by: ip, address, instruction, old, new });
+        }
+    }
+    Ok(event)
+}
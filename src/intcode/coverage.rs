@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+use super::disasm::{Line, disassemble_from};
+use super::{Memory, Profile};
+
+/// Which instructions of a program have run, summed over any number of runs.
+///
+/// Each run is profiled and its counts added with `add`:
+///
+/// ```ignore
+/// let mut coverage = Coverage::default();
+/// for input in inputs {
+///     let mut m = new_machine(program.clone());
+///     m.start_profile();
+///     // feed input and run
+///     coverage.add(&m.take_profile().unwrap());
+/// }
+/// let listing = coverage.listing(&program);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Executions per instruction address.
+    pub hits: BTreeMap<i128, u64>,
+    /// Per `jt`/`jf` address, times it jumped and times it fell through.
+    pub branches: BTreeMap<i128, (u64, u64)>,
+}
+
+impl Coverage {
+    pub fn add(&mut self, profile: &Profile) {
+        for (&address, &count) in &profile.by_address {
+            *self.hits.entry(address).or_insert(0) += count;
+        }
+        for (&address, &(taken, not_taken)) in &profile.branches {
+            let branch = self.branches.entry(address).or_insert((0, 0));
+            branch.0 += taken;
+            branch.1 += not_taken;
+        }
+    }
+
+    /// The disassembly the coverage is reported against. Everything that ran is
+    /// disassembled as code, even if it could not be found statically.
+    pub fn listing(&self, program: &Memory) -> Vec<Line> {
+        let mut entries = vec![0];
+        entries.extend(self.hits.keys().cloned());
+        disassemble_from(program, &entries)
+    }
+
+    /// An lcov tracefile for `listing`, saved as `source`. Line numbers are lines of the
+    /// listing counting from 1. Every instruction line is instrumented, data lines are not,
+    /// and each `jt`/`jf` has two branches, jumping and falling through.
+    pub fn lcov(&self, listing: &[Line], source: &str) -> String {
+        let mut out = format!("TN:\nSF:{}\n", source);
+        let (mut found, mut hit) = (0, 0);
+        let (mut branches_found, mut branches_hit) = (0, 0);
+        for (i, line) in listing.iter().enumerate() {
+            let instr = match line {
+                Line::Instruction(instr) => instr,
+                Line::Data { .. } => continue,
+            };
+            let number = i + 1;
+            if instr.opcode == 5 || instr.opcode == 6 {
+                let branch = self.branches.get(&instr.address);
+                for (k, count) in [branch.map(|b| b.0), branch.map(|b| b.1)].iter().enumerate() {
+                    match count {
+                        Some(count) => out += &format!("BRDA:{},0,{},{}\n", number, k, count),
+                        // lcov's way of saying the branch was never reached
+                        None => out += &format!("BRDA:{},0,{},-\n", number, k),
+                    }
+                    branches_found += 1;
+                    if count.unwrap_or(0) > 0 {
+                        branches_hit += 1;
+                    }
+                }
+            }
+            let count = self.hits.get(&instr.address).cloned().unwrap_or(0);
+            out += &format!("DA:{},{}\n", number, count);
+            found += 1;
+            if count > 0 {
+                hit += 1;
+            }
+        }
+        out += &format!("BRF:{}\nBRH:{}\n", branches_found, branches_hit);
+        out += &format!("LF:{}\nLH:{}\nend_of_record\n", found, hit);
+        out
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt;
+
+use super::disasm::{Line, decode};
+use super::{Event, IntcodeError, Limit, Machine, run_one_step, write_address};
+
+/// Why the debugger gave control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The requested number of steps ran.
+    Stepped,
+    /// The ip reached a breakpoint. The instruction there has not run yet.
+    Breakpoint(i128),
+    /// The instruction at `ip` changed a watched cell.
+    Watchpoint { ip: i128, address: i128, old: i128, new: i128 },
+    NeedsInput,
+    Halted,
+    Error(IntcodeError),
+    /// The machine reached one of its limits before the instruction at the ip.
+    Limit(Limit),
+    /// Stepping back reached the first recorded instruction.
+    StartOfHistory,
+    /// Stepping back reached the oldest instruction still recorded. This many run before it
+    /// were dropped to keep within `Debugger::history_depth`.
+    HistoryDropped(u64),
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Stop::Stepped => write!(f, "stepped"),
+            Stop::Breakpoint(ip) => write!(f, "breakpoint at {}", ip),
+            Stop::Watchpoint { ip, address, old, new } =>
+                write!(f, "watchpoint [{}]: {} -> {} by ip {}", address, old, new, ip),
+            Stop::NeedsInput => write!(f, "waiting for input"),
+            Stop::Halted => write!(f, "halted"),
+            Stop::Error(e) => write!(f, "error: {}", e),
+            Stop::Limit(limit) => write!(f, "{}", limit),
+            Stop::StartOfHistory => write!(f, "at start of history"),
+            Stop::HistoryDropped(dropped) =>
+                write!(f, "at oldest kept history, {} earlier instructions dropped", dropped),
+        }
+    }
+}
+
+const HELP: &str = "\
+s, step [n]        run n instructions (default 1)
+c, continue        run until a breakpoint, watchpoint, input wait, halt or error
+sb, back [n]       undo n instructions (default 1)
+rc, reverse        undo instructions until a breakpoint or watched write
+b, break <ip>      stop before the instruction at ip
+d, delete <ip>     remove a breakpoint
+w, watch <addr>    stop after any instruction that changes [addr]
+u, unwatch <addr>  remove a watchpoint
+r, regs            show ip, relative base and the current instruction
+x <addr> [n]       dump n words of memory (default 8)
+l, list [addr] [n] disassemble n instructions (default 10) from addr (default ip)
+i, input <v>,...   queue numbers as input
+line <text>        queue text as ASCII input, followed by a newline
+info               list breakpoints, watchpoints and how much history is kept
+q, quit            leave the debugger";
+
+/// What it takes to undo one instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Undo {
+    ip: i128,
+    base: i128,
+    /// The address written and the value it held before.
+    write: Option<(i128, i128)>,
+    /// The input value the instruction took off the queue.
+    input: Option<i128>,
+    output: bool,
+}
+
+/// A machine under a debugger. Everything goes through `run_one_step`, so it works on any
+/// program, interactive ones included: when the program waits for input, queue some and continue.
+///
+/// Every instruction run is logged with the ip, base and memory it overwrote, so the machine
+/// can be stepped backwards as far as it was stepped forwards, up to `history_depth` instructions.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    pub machine: Machine,
+    pub breakpoints: BTreeSet<i128>,
+    /// Watched addresses with the value last seen there.
+    pub watchpoints: BTreeMap<i128, i128>,
+    /// Output produced since it was last taken.
+    pub output: Vec<i128>,
+    /// Most instructions kept for stepping back. The oldest are dropped as new ones run.
+    pub history_depth: usize,
+    history: VecDeque<Undo>,
+    /// Instructions run before the oldest one in `history`.
+    dropped: u64,
+}
+
+/// Instructions a new debugger keeps for stepping back, which takes about 128MB.
+pub const DEFAULT_HISTORY_DEPTH: usize = 1 << 20;
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Debugger {
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            output: Vec::new(),
+            history_depth: DEFAULT_HISTORY_DEPTH,
+            history: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Number of instructions that can be stepped back.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Number of instructions run that can no longer be stepped back.
+    pub fn history_dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn watch(&mut self, address: i128) {
+        let value = self.machine.ram.get(address);
+        self.watchpoints.insert(address, value);
+    }
+
+    /// Runs one instruction. Gives a stop reason if the machine could not carry on past it.
+    pub fn step(&mut self) -> Option<Stop> {
+        let ip = self.machine.ip;
+        let mut undo = Undo { ip, base: self.machine.base, write: None, input: None, output: false };
+        // if this fails the step will fail the same way, and there is nothing to undo
+        if let Ok(Some(address)) = write_address(&self.machine) {
+            undo.write = Some((address, self.machine.ram.get(address)));
+        }
+        let queued = self.machine.input.front().cloned();
+        let before = self.machine.input.len();
+        match run_one_step(&mut self.machine) {
+            Err(e) => return Some(Stop::Error(e)),
+            Ok(Some(Event::NeedsInput)) => return Some(Stop::NeedsInput),
+            Ok(Some(Event::Halted)) => return Some(Stop::Halted),
+            Ok(Some(Event::Limit(limit))) => return Some(Stop::Limit(limit)),
+            Ok(Some(Event::Output(value))) => {
+                self.output.push(value);
+                undo.output = true;
+            }
+            Ok(None) => {}
+        }
+        if self.machine.input.len() < before {
+            undo.input = queued;
+        }
+        self.history.push_back(undo);
+        while self.history.len() > self.history_depth {
+            self.history.pop_front();
+            self.dropped += 1;
+        }
+        for (&address, last) in self.watchpoints.iter_mut() {
+            let value = self.machine.ram.get(address);
+            if value != *last {
+                let old = *last;
+                *last = value;
+                return Some(Stop::Watchpoint { ip, address, old, new: value });
+            }
+        }
+        None
+    }
+
+    /// Runs up to `count` instructions, stopping early at a breakpoint after the first.
+    pub fn step_n(&mut self, count: usize) -> Stop {
+        for i in 0..count {
+            if i > 0 && self.breakpoints.contains(&self.machine.ip) {
+                return Stop::Breakpoint(self.machine.ip);
+            }
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+        }
+        Stop::Stepped
+    }
+
+    /// Runs until something stops the machine. A breakpoint at the current ip is stepped over.
+    pub fn cont(&mut self) -> Stop {
+        self.step_n(usize::MAX)
+    }
+
+    /// Undoes the last instruction run. Gives a stop reason if that changed a watched cell
+    /// or there was nothing left to undo.
+    pub fn step_back(&mut self) -> Option<Stop> {
+        let undo = match self.history.pop_back() {
+            Some(undo) => undo,
+            None if self.dropped > 0 => return Some(Stop::HistoryDropped(self.dropped)),
+            None => return Some(Stop::StartOfHistory),
+        };
+        let mut stop = None;
+        if let Some((address, old)) = undo.write {
+            let new = self.machine.ram.get(address);
+            self.machine.ram.set(address, old);
+            if let Some(last) = self.watchpoints.get_mut(&address) {
+                *last = old;
+                if new != old {
+                    stop = Some(Stop::Watchpoint { ip: undo.ip, address, old, new });
+                }
+            }
+        }
+        self.machine.ip = undo.ip;
+        self.machine.base = undo.base;
+        self.machine.steps -= 1;
+        if let Some(value) = undo.input {
+            self.machine.input.push_front(value);
+        }
+        if undo.output {
+            self.output.pop();
+        }
+        stop
+    }
+
+    /// Undoes up to `count` instructions, stopping once the ip is back on a breakpoint.
+    pub fn step_back_n(&mut self, count: usize) -> Stop {
+        for _ in 0..count {
+            if let Some(stop) = self.step_back() {
+                return stop;
+            }
+            if self.breakpoints.contains(&self.machine.ip) {
+                return Stop::Breakpoint(self.machine.ip);
+            }
+        }
+        Stop::Stepped
+    }
+
+    /// Undoes instructions until one at a breakpoint is next to run again, or the one that
+    /// wrote a watched cell is. That instruction has not been run again yet.
+    pub fn reverse_continue(&mut self) -> Stop {
+        self.step_back_n(usize::MAX)
+    }
+
+    /// The instruction at `address`, as a disassembly line.
+    fn describe(&self, address: i128) -> String {
+        if address < 0 {
+            return format!("{:>6}: negative address", address);
+        }
+        match decode(&self.machine.ram, address) {
+            Some(instr) => Line::Instruction(instr).to_string(),
+            None => Line::Data { address, words: vec![self.machine.ram.get(address)] }.to_string(),
+        }
+    }
+
+    /// Output since the last call, as text if it looks like lines of ASCII, otherwise as numbers.
+    fn take_output(&mut self) -> String {
+        let output: Vec<i128> = self.output.drain(..).collect();
+        if output.is_empty() {
+            return String::new();
+        }
+        let printable = |x: &i128| (32..127).contains(x);
+        if output.contains(&10) && output.iter().any(printable) && output.iter().all(|x| *x == 10 || printable(x)) {
+            let mut text: String = output.iter().map(|&x| x as u8 as char).collect();
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            format!("output:\n{}", text)
+        } else {
+            let values: Vec<String> = output.iter().map(|x| x.to_string()).collect();
+            format!("output: {}\n", values.join(","))
+        }
+    }
+
+    fn stopped(&mut self, stop: Stop) -> String {
+        let mut text = self.take_output();
+        if stop != Stop::Stepped {
+            text += &format!("{}\n", stop);
+        }
+        text + &self.describe(self.machine.ip)
+    }
+
+    /// Runs one debugger command and gives the text to show for it,
+    /// or `None` if the command was to quit.
+    pub fn execute(&mut self, command: &str) -> Option<String> {
+        let command = command.trim();
+        let (name, rest) = match command.find(' ') {
+            Some(i) => (&command[..i], command[i + 1..].trim()),
+            None => (command, ""),
+        };
+        let numbers: Result<Vec<i128>, _> = rest
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|a| !a.is_empty())
+            .map(|a| a.parse())
+            .collect();
+        let numbers = match numbers {
+            Ok(numbers) => numbers,
+            Err(_) if name == "line" => Vec::new(),
+            Err(_) => return Some(format!("bad number in '{}'", rest)),
+        };
+        let arg = |i: usize, default: i128| numbers.get(i).cloned().unwrap_or(default);
+        let text = match name {
+            "" => String::new(),
+            "q" | "quit" => return None,
+            "h" | "help" => HELP.to_string(),
+            "s" | "step" => {
+                let count = arg(0, 1).max(1) as usize;
+                let stop = self.step_n(count);
+                self.stopped(stop)
+            }
+            "c" | "continue" => {
+                let stop = self.cont();
+                self.stopped(stop)
+            }
+            "sb" | "back" => {
+                let count = arg(0, 1).max(1) as usize;
+                let stop = self.step_back_n(count);
+                self.stopped(stop)
+            }
+            "rc" | "reverse" => {
+                let stop = self.reverse_continue();
+                self.stopped(stop)
+            }
+            "b" | "break" | "d" | "delete" | "w" | "watch" | "u" | "unwatch" if numbers.len() != 1 => {
+                format!("usage: {} <address>", name)
+            }
+            "w" | "watch" | "x" | "l" | "list" if matches!(numbers.first(), Some(&address) if address < 0) => {
+                format!("negative address {}", numbers[0])
+            }
+            "b" | "break" => {
+                self.breakpoints.insert(numbers[0]);
+                format!("breakpoint at {}", numbers[0])
+            }
+            "d" | "delete" => {
+                if self.breakpoints.remove(&numbers[0]) {
+                    format!("deleted breakpoint at {}", numbers[0])
+                } else {
+                    format!("no breakpoint at {}", numbers[0])
+                }
+            }
+            "w" | "watch" => {
+                self.watch(numbers[0]);
+                format!("watching [{}] = {}", numbers[0], self.machine.ram.get(numbers[0]))
+            }
+            "u" | "unwatch" => {
+                if self.watchpoints.remove(&numbers[0]).is_some() {
+                    format!("stopped watching [{}]", numbers[0])
+                } else {
+                    format!("not watching [{}]", numbers[0])
+                }
+            }
+            "r" | "regs" => format!("ip {} base {}\n{}", self.machine.ip, self.machine.base,
+                self.describe(self.machine.ip)),
+            "x" if numbers.is_empty() => "usage: x <address> [count]".to_string(),
+            "x" => {
+                let start = numbers[0];
+                // stop at the end of the address space
+                let count = arg(1, 8).max(1).min((i128::MAX - start).saturating_add(1));
+                let mut rows = Vec::new();
+                for row in (0..count).step_by(8) {
+                    let values: Vec<String> = (row..count.min(row + 8))
+                        .map(|i| self.machine.ram.get(start + i).to_string())
+                        .collect();
+                    rows.push(format!("{:>6}: {}", start + row, values.join(" ")));
+                }
+                rows.join("\n")
+            }
+            "l" | "list" => {
+                let mut address = arg(0, self.machine.ip);
+                let mut lines = Vec::new();
+                for _ in 0..arg(1, 10).max(1) {
+                    let marker = if address == self.machine.ip { "=>" } else { "  " };
+                    lines.push(format!("{}{}", marker, self.describe(address)));
+                    let size = decode(&self.machine.ram, address).map(|i| i.size() as i128).unwrap_or(1);
+                    address = match address.checked_add(size) {
+                        Some(next) => next,
+                        None => break,
+                    };
+                }
+                lines.join("\n")
+            }
+            "i" | "input" if numbers.is_empty() => "usage: input <value>,...".to_string(),
+            "i" | "input" => {
+                for &value in &numbers {
+                    self.machine.push_input(value);
+                }
+                format!("{} values queued", self.machine.input.len())
+            }
+            "line" => {
+                for c in rest.chars().chain(Some('\n')) {
+                    self.machine.push_input(c as i128);
+                }
+                format!("{} values queued", self.machine.input.len())
+            }
+            "info" => {
+                let breakpoints: Vec<String> = self.breakpoints.iter().map(|b| b.to_string()).collect();
+                let watchpoints: Vec<String> = self.watchpoints.keys().map(|w| format!("[{}]", w)).collect();
+                format!("breakpoints: {}\nwatchpoints: {}\nhistory: {} kept, {} dropped, depth {}", breakpoints.join(" "),
+                    watchpoints.join(" "), self.history.len(), self.dropped, self.history_depth)
+            }
+            _ => format!("unknown command '{}', try help", name),
+        };
+        Some(text)
+    }
+}
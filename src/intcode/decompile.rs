@@ -0,0 +1,855 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use super::cfg::{Block, Cfg, Exit, pushed};
+use super::disasm::{Instruction, Operand, write_param};
+use super::Memory;
+
+/// Stands for leaving the function in the graph post-dominators are worked out on.
+const EXIT: i128 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Lt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Lt => "<",
+            Op::Ge => ">=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Op::Mul => 3,
+            Op::Add | Op::Sub => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Num(i128),
+    Var(String),
+    Input,
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Bin(op, _, _) => op.precedence(),
+            _ => 4,
+        }
+    }
+
+    fn bin(op: Op, a: Expr, b: Expr) -> Expr {
+        Expr::Bin(op, Box::new(a), Box::new(b))
+    }
+}
+
+fn add(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Num(x), Expr::Num(y)) => Expr::Num(x.wrapping_add(y)),
+        (Expr::Num(0), e) | (e, Expr::Num(0)) => e,
+        (e, Expr::Num(k)) | (Expr::Num(k), e) if k < 0 && k > i128::MIN => Expr::bin(Op::Sub, e, Expr::Num(-k)),
+        (a, b) => Expr::bin(Op::Add, a, b),
+    }
+}
+
+fn mul(a: Expr, b: Expr) -> Expr {
+    match (a, b) {
+        (Expr::Num(x), Expr::Num(y)) => Expr::Num(x.wrapping_mul(y)),
+        (Expr::Num(1), e) | (e, Expr::Num(1)) => e,
+        (Expr::Num(-1), e) | (e, Expr::Num(-1)) => neg(e),
+        (a, b) => Expr::bin(Op::Mul, a, b),
+    }
+}
+
+fn neg(e: Expr) -> Expr {
+    match e {
+        Expr::Num(x) => Expr::Num(x.wrapping_neg()),
+        Expr::Neg(inner) => *inner,
+        e => Expr::Neg(Box::new(e)),
+    }
+}
+
+/// A value tested by `jt` as a comparison.
+fn truthy(e: Expr) -> Expr {
+    match e {
+        Expr::Bin(op, _, _) if op.precedence() == 1 => e,
+        e => Expr::bin(Op::Ne, e, Expr::Num(0)),
+    }
+}
+
+/// The opposite of a comparison.
+fn negate(cond: Expr) -> Expr {
+    match cond {
+        Expr::Bin(op, a, b) => {
+            let op = match op {
+                Op::Lt => Op::Ge,
+                Op::Ge => Op::Lt,
+                Op::Eq => Op::Ne,
+                Op::Ne => Op::Eq,
+                _ => return negate(truthy(Expr::Bin(op, a, b))),
+            };
+            Expr::Bin(op, a, b)
+        }
+        e => negate(truthy(e)),
+    }
+}
+
+fn parenthesized(e: &Expr, min: u8) -> String {
+    if e.precedence() < min { format!("({})", e) } else { e.to_string() }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Num(x) => write!(f, "{}", x),
+            Expr::Var(name) => f.write_str(name),
+            Expr::Input => f.write_str("input()"),
+            Expr::Neg(e) => write!(f, "-{}", parenthesized(e, 4)),
+            Expr::Bin(op, a, b) => {
+                let p = op.precedence();
+                // comparisons do not chain, and the right side of a subtraction groups
+                let (left, right) = match op {
+                    Op::Add | Op::Mul => (p, p),
+                    Op::Sub => (p, p + 1),
+                    _ => (p + 1, p + 1),
+                };
+                write!(f, "{} {} {}", parenthesized(a, left), op.symbol(), parenthesized(b, right))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stmt {
+    /// `patches` is where the instruction starts if the cell written is reachable code.
+    Assign { dest: String, value: Expr, patches: Option<i128> },
+    Output(Expr),
+    Call { result: Option<String>, callee: String, args: Vec<Expr> },
+    Base(Expr),
+    If { cond: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt> },
+    /// `while cond`, or `loop` without one.
+    Loop { cond: Option<Expr>, body: Vec<Stmt> },
+    Label(i128),
+    Goto(i128),
+    GotoComputed(Expr),
+    Break,
+    Continue,
+    Return(Option<String>),
+    Halt,
+}
+
+impl Stmt {
+    fn is_jump(&self) -> bool {
+        matches!(self, Stmt::Goto(_) | Stmt::GotoComputed(_) | Stmt::Break | Stmt::Continue | Stmt::Return(_) | Stmt::Halt)
+    }
+}
+
+/// How a lowered block hands on control.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Goto(i128),
+    Branch { cond: Expr, then: i128, otherwise: i128 },
+    /// The block's statements end in a return, halt or jump through memory.
+    End,
+}
+
+impl Term {
+    fn targets(&self) -> Vec<i128> {
+        match *self {
+            Term::Goto(to) => vec![to],
+            Term::Branch { then, otherwise, .. } => vec![then, otherwise],
+            Term::End => Vec::new(),
+        }
+    }
+}
+
+/// What the decompiler worked out about one function, or the main program at 0.
+#[derive(Debug, Clone, Copy)]
+struct Function {
+    entry: i128,
+    /// Words the prologue's `arb` reserves: the return address, the arguments, then the locals.
+    frame: i128,
+    /// The most argument cells any caller fills in.
+    arity: i128,
+    /// Whether it writes its first argument, which is where callers find the result.
+    returns: bool,
+}
+
+/// The cell an instruction writes, if any.
+fn written(instr: &Instruction) -> Option<Operand> {
+    write_param(instr.opcode).map(|i| instr.operands[i])
+}
+
+/// The cells an instruction reads.
+fn reads(instr: &Instruction) -> impl Iterator<Item = Operand> + '_ {
+    let write = write_param(instr.opcode);
+    instr.operands.iter()
+        .enumerate()
+        .filter(move |&(i, operand)| Some(i) != write && !matches!(operand, Operand::Immediate(_)))
+        .map(|(_, &operand)| operand)
+}
+
+/// The frame size an `arb` at the start of a function reserves.
+fn prologue(instr: &Instruction) -> Option<i128> {
+    match (instr.opcode, &instr.operands[..]) {
+        (9, [Operand::Immediate(size)]) if *size > 0 => Some(*size),
+        _ => None,
+    }
+}
+
+/// The highest argument cell, counting from `rb+1`, that instructions fill in.
+fn filled(instrs: &[&Instruction]) -> i128 {
+    instrs.iter()
+        .filter_map(|&instr| match written(instr) {
+            Some(Operand::Relative(offset)) if offset > 0 => Some(offset),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+struct Decompiler<'a> {
+    cfg: &'a Cfg,
+    functions: BTreeMap<i128, Function>,
+    /// For each cell of reachable code, where its instruction starts.
+    code: BTreeMap<i128, i128>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(cfg: &'a Cfg) -> Decompiler<'a> {
+        let mut code = BTreeMap::new();
+        for instr in cfg.blocks.values().flat_map(|b| b.instructions.iter()) {
+            for address in instr.address..instr.address + instr.size() as i128 {
+                code.insert(address, instr.address);
+            }
+        }
+
+        let mut functions = BTreeMap::new();
+        for &entry in Some(&0).into_iter().chain(cfg.functions.keys()) {
+            let frame = cfg.blocks.get(&entry)
+                .and_then(|b| b.instructions.first())
+                .and_then(prologue)
+                .unwrap_or(0);
+            functions.insert(entry, Function { entry, frame, arity: 0, returns: false });
+        }
+        for block in cfg.blocks.values() {
+            if let [Exit::Call { target, .. }] = block.exits[..] {
+                let instrs: Vec<&Instruction> = block.instructions.iter().collect();
+                let callee = functions.get_mut(&target).unwrap();
+                callee.arity = callee.arity.max(filled(&instrs).min(callee.frame - 1));
+            }
+        }
+        for f in functions.values_mut() {
+            let first = Some(Operand::Relative(1 - f.frame));
+            f.returns = f.arity > 0 && cfg.body(f.entry).iter()
+                .flat_map(|start| cfg.blocks[start].instructions.iter())
+                .any(|instr| written(instr) == first);
+        }
+        Decompiler { cfg, functions, code }
+    }
+
+    /// The name of the cell `offset` from the base: `ret` for the return address, `a1`
+    /// and up for arguments, `l1` and up for locals, and `t1` and up for the cells past
+    /// the frame that calls fill in.
+    fn var(&self, f: &Function, offset: i128) -> String {
+        let slot = f.frame + offset;
+        if offset >= 0 {
+            format!("t{}", offset)
+        } else if slot < 0 {
+            format!("rb[{}]", offset)
+        } else if f.entry == 0 {
+            format!("l{}", slot + 1)
+        } else if slot == 0 {
+            "ret".to_string()
+        } else if slot <= f.arity {
+            format!("a{}", slot)
+        } else {
+            format!("l{}", slot - f.arity)
+        }
+    }
+
+    fn operand(&self, f: &Function, operand: Operand) -> Expr {
+        match operand {
+            Operand::Position(address) => Expr::Var(format!("m{}", address)),
+            Operand::Immediate(value) => Expr::Num(value),
+            Operand::Relative(offset) => Expr::Var(self.var(f, offset)),
+        }
+    }
+
+    /// The name of a cell written to. Immediate mode writes act like position mode.
+    fn dest(&self, f: &Function, operand: Operand) -> String {
+        match operand {
+            Operand::Position(address) | Operand::Immediate(address) => format!("m{}", address),
+            Operand::Relative(offset) => self.var(f, offset),
+        }
+    }
+
+    /// What an instruction that writes stores.
+    fn value(&self, f: &Function, instr: &Instruction) -> Expr {
+        let arg = |i: usize| self.operand(f, instr.operands[i]);
+        match instr.opcode {
+            1 => add(arg(0), arg(1)),
+            2 => mul(arg(0), arg(1)),
+            7 => Expr::bin(Op::Lt, arg(0), arg(1)),
+            8 => Expr::bin(Op::Eq, arg(0), arg(1)),
+            _ => Expr::Input,
+        }
+    }
+
+    fn statements(&self, f: &Function, instrs: &[&Instruction]) -> Vec<Stmt> {
+        instrs.iter()
+            .filter_map(|&instr| match (instr.opcode, written(instr)) {
+                (4, _) => Some(Stmt::Output(self.operand(f, instr.operands[0]))),
+                (9, _) => Some(Stmt::Base(self.operand(f, instr.operands[0]))),
+                (_, Some(dest)) if self.value(f, instr) == self.operand(f, dest) => None,
+                (_, Some(dest)) => {
+                    let patches = match dest {
+                        Operand::Position(address) | Operand::Immediate(address) => self.code.get(&address).cloned(),
+                        Operand::Relative(_) => None,
+                    };
+                    Some(Stmt::Assign { dest: self.dest(f, dest), value: self.value(f, instr), patches })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the value the instruction at `i` writes can be worked out later, at the
+    /// end of `instrs`, instead.
+    fn movable(instrs: &[&Instruction], i: usize) -> bool {
+        let instr = instrs[i];
+        let dest = written(instr);
+        let sources: Vec<Operand> = reads(instr).collect();
+        instr.opcode != 3 && instrs[i + 1..].iter().all(|later| {
+            !reads(later).any(|operand| Some(operand) == dest)
+                && !matches!(written(later), Some(operand) if sources.contains(&operand))
+        })
+    }
+
+    /// Takes the push of the return address and the instructions filling in the
+    /// arguments out of `instrs`, giving the arguments' values.
+    fn call(&self, f: &Function, instrs: &mut Vec<&Instruction>, returns: i128, arity: i128) -> Vec<Expr> {
+        let push = instrs.iter()
+            .rposition(|instr| pushed(instr) == Some(returns) && instr.operands[2] == Operand::Relative(0));
+        if let Some(i) = push {
+            instrs.remove(i);
+        }
+        (1..=arity)
+            .map(|offset| {
+                let cell = Some(Operand::Relative(offset));
+                match instrs.iter().rposition(|&instr| written(instr) == cell) {
+                    Some(i) if Decompiler::movable(instrs, i) => {
+                        let instr = instrs.remove(i);
+                        self.value(f, instr)
+                    }
+                    _ => Expr::Var(self.var(f, offset)),
+                }
+            })
+            .collect()
+    }
+
+    /// The condition a conditional jump is taken on, folding in the comparison just
+    /// before it when that is what it tests.
+    fn condition(&self, f: &Function, instrs: &mut Vec<&Instruction>, jump: &Instruction) -> Expr {
+        let tested = jump.operands[0];
+        let cond = match instrs.last() {
+            Some(prev) if matches!(prev.opcode, 7 | 8) && prev.operands[2] == tested => {
+                let prev = instrs.pop().unwrap();
+                self.value(f, prev)
+            }
+            _ => truthy(self.operand(f, tested)),
+        };
+        if jump.opcode == 5 { cond } else { negate(cond) }
+    }
+
+    /// A block as statements and how it carries on, with the prologue and epilogue
+    /// `arb`s and the call idiom folded away.
+    fn lower(&self, f: &Function, block: &Block) -> (Vec<Stmt>, Term) {
+        let mut instrs: Vec<&Instruction> = block.instructions.iter().collect();
+        if block.start == f.entry && f.frame > 0 {
+            instrs.remove(0);
+        }
+        let last = block.instructions.last().unwrap();
+        if matches!(last.opcode, 5 | 6 | 99) {
+            instrs.pop();
+        }
+        let mut tail = Vec::new();
+        let term = match block.exits[..] {
+            [Exit::Halt] => {
+                tail.push(Stmt::Halt);
+                Term::End
+            }
+            [Exit::Return] => {
+                let epilogue = Some(&Operand::Immediate(-f.frame));
+                if f.frame > 0 && matches!(instrs.last(), Some(i) if i.opcode == 9 && i.operands.first() == epilogue) {
+                    instrs.pop();
+                }
+                let result = if f.returns { Some(self.var(f, 1 - f.frame)) } else { None };
+                tail.push(Stmt::Return(result));
+                Term::End
+            }
+            [Exit::Call { target, returns }] => {
+                let callee = self.functions[&target];
+                let args = self.call(f, &mut instrs, returns, callee.arity);
+                let result = if callee.returns { Some(self.var(f, 1)) } else { None };
+                tail.push(Stmt::Call { result, callee: format!("f{}", target), args });
+                Term::Goto(returns)
+            }
+            [Exit::ComputedCall { returns }] => {
+                let arity = filled(&instrs);
+                let args = self.call(f, &mut instrs, returns, arity);
+                let result = if arity > 0 { Some(self.var(f, 1)) } else { None };
+                let callee = format!("(*{})", self.operand(f, last.operands[1]));
+                tail.push(Stmt::Call { result, callee, args });
+                Term::Goto(returns)
+            }
+            [Exit::Computed] => {
+                tail.push(Stmt::GotoComputed(self.operand(f, last.operands[1])));
+                Term::End
+            }
+            [Exit::Jump(to)] | [Exit::Fall(to)] => Term::Goto(to),
+            [Exit::Jump(then), Exit::Fall(otherwise)] => {
+                let cond = self.condition(f, &mut instrs, last);
+                if then == otherwise { Term::Goto(then) } else { Term::Branch { cond, then, otherwise } }
+            }
+            [Exit::Computed, Exit::Fall(otherwise)] => {
+                let cond = self.condition(f, &mut instrs, last);
+                let then = vec![Stmt::GotoComputed(self.operand(f, last.operands[1]))];
+                tail.push(Stmt::If { cond, then, otherwise: Vec::new() });
+                Term::Goto(otherwise)
+            }
+            _ => Term::End,
+        };
+        let mut stmts = self.statements(f, &instrs);
+        stmts.extend(tail);
+        (stmts, term)
+    }
+
+    fn function(&self, f: &Function) -> Vec<Stmt> {
+        let lowered = self.cfg.body(f.entry).into_iter()
+            .map(|start| (start, self.lower(f, &self.cfg.blocks[&start])))
+            .collect();
+        let mut structurer = Structurer::new(f.entry, lowered);
+        let mut stmts = structurer.seq(Some(f.entry), None);
+        let mut targets = BTreeSet::new();
+        gotos(&stmts, &mut targets);
+        drop_labels(&mut stmts, &targets);
+        tidy(stmts)
+    }
+}
+
+/// Blocks in reverse postorder from `entry`.
+fn reverse_postorder(entry: i128, next: &BTreeMap<i128, Vec<i128>>) -> Vec<i128> {
+    let mut order = Vec::new();
+    let mut seen = BTreeSet::new();
+    seen.insert(entry);
+    let mut stack = vec![(entry, 0)];
+    while let Some((node, i)) = stack.pop() {
+        match next.get(&node).and_then(|succ| succ.get(i)) {
+            Some(&succ) => {
+                stack.push((node, i + 1));
+                if seen.insert(succ) {
+                    stack.push((succ, 0));
+                }
+            }
+            None => order.push(node),
+        }
+    }
+    order.reverse();
+    order
+}
+
+/// For each node reached from `entry`, the nodes every path to it goes through.
+fn dominators(entry: i128, next: &BTreeMap<i128, Vec<i128>>) -> BTreeMap<i128, BTreeSet<i128>> {
+    let order = reverse_postorder(entry, next);
+    let mut prev: BTreeMap<i128, Vec<i128>> = BTreeMap::new();
+    for node in &order {
+        for &succ in next.get(node).into_iter().flatten() {
+            prev.entry(succ).or_default().push(*node);
+        }
+    }
+    let all: BTreeSet<i128> = order.iter().cloned().collect();
+    let mut dom: BTreeMap<i128, BTreeSet<i128>> = order.iter().map(|&node| (node, all.clone())).collect();
+    dom.insert(entry, Some(entry).into_iter().collect());
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in &order[1..] {
+            let mut common = prev[&node].iter()
+                .map(|p| &dom[p])
+                .fold(None, |common: Option<BTreeSet<i128>>, d| Some(match common {
+                    Some(common) => common.intersection(d).cloned().collect(),
+                    None => d.clone(),
+                }))
+                .unwrap_or_default();
+            common.insert(node);
+            if common != dom[&node] {
+                dom.insert(node, common);
+                changed = true;
+            }
+        }
+    }
+    dom
+}
+
+struct Loop {
+    body: BTreeSet<i128>,
+    /// Where control goes once the loop is done: the one block it leaves to, or else
+    /// where all the ways out meet again.
+    follow: Option<i128>,
+}
+
+/// Turns the blocks of one function into nested statements: back edges to a block that
+/// dominates them into loops, and branches into ifs that join again at the branch's
+/// immediate post-dominator. Whatever does not fit becomes a `goto`.
+struct Structurer {
+    lowered: BTreeMap<i128, (Vec<Stmt>, Term)>,
+    /// Immediate post-dominators, leaving out blocks whose paths only join by returning.
+    joins: BTreeMap<i128, i128>,
+    loops: BTreeMap<i128, Loop>,
+    done: BTreeSet<i128>,
+    /// Headers of the loops being structured, innermost last.
+    stack: Vec<i128>,
+}
+
+impl Structurer {
+    fn new(entry: i128, lowered: BTreeMap<i128, (Vec<Stmt>, Term)>) -> Structurer {
+        let next: BTreeMap<i128, Vec<i128>> = lowered.iter()
+            .map(|(&start, (_, term))| (start, term.targets().into_iter().filter(|t| lowered.contains_key(t)).collect()))
+            .collect();
+
+        let mut prev: BTreeMap<i128, Vec<i128>> = BTreeMap::new();
+        let mut reverse: BTreeMap<i128, Vec<i128>> = BTreeMap::new();
+        for (&node, succs) in &next {
+            if succs.is_empty() {
+                reverse.entry(EXIT).or_default().push(node);
+            }
+            for &succ in succs {
+                prev.entry(succ).or_default().push(node);
+                reverse.entry(succ).or_default().push(node);
+            }
+        }
+
+        let pdom = dominators(EXIT, &reverse);
+        let mut joins = BTreeMap::new();
+        for (&node, after) in pdom.iter().filter(|&(&node, _)| node != EXIT) {
+            let join = after.iter().find(|&&d| d != node && pdom[&d].len() == after.len() - 1);
+            if let Some(&join) = join.filter(|&&d| d != EXIT) {
+                joins.insert(node, join);
+            }
+        }
+
+        let dom = dominators(entry, &next);
+        let mut bodies: BTreeMap<i128, BTreeSet<i128>> = BTreeMap::new();
+        for (&node, succs) in &next {
+            for &header in succs.iter().filter(|h| matches!(dom.get(&node), Some(d) if d.contains(h))) {
+                let body = bodies.entry(header).or_insert_with(|| Some(header).into_iter().collect());
+                let mut work = vec![node];
+                while let Some(n) = work.pop() {
+                    if body.insert(n) {
+                        work.extend(prev.get(&n).into_iter().flatten());
+                    }
+                }
+            }
+        }
+        let loops = bodies.into_iter()
+            .map(|(header, body)| {
+                let exits: BTreeSet<i128> = body.iter()
+                    .flat_map(|n| next[n].iter().cloned())
+                    .filter(|succ| !body.contains(succ))
+                    .collect();
+                let mut follow = joins.get(&header).cloned();
+                while let Some(join) = follow.filter(|join| body.contains(join)) {
+                    follow = joins.get(&join).cloned();
+                }
+                if exits.len() == 1 {
+                    follow = exits.iter().next().cloned();
+                }
+                (header, Loop { body, follow })
+            })
+            .collect();
+
+        Structurer { lowered, joins, loops, done: BTreeSet::new(), stack: Vec::new() }
+    }
+
+    /// A `continue`, `break` or `goto` if going to `start` leaves the straight line
+    /// through the innermost loop.
+    fn loop_jump(&self, start: i128) -> Option<Stmt> {
+        for (depth, header) in self.stack.iter().enumerate().rev() {
+            let innermost = depth + 1 == self.stack.len();
+            if start == *header && self.done.contains(header) {
+                return Some(if innermost { Stmt::Continue } else { Stmt::Goto(start) });
+            }
+            if Some(start) == self.loops[header].follow {
+                return Some(if innermost { Stmt::Break } else { Stmt::Goto(start) });
+            }
+        }
+        None
+    }
+
+    /// Where the two sides of a branch meet again, if that is inside the innermost loop.
+    fn join(&self, start: i128) -> Option<i128> {
+        let join = self.joins.get(&start).cloned();
+        match self.stack.last() {
+            Some(header) => join.filter(|j| self.loops[header].body.contains(j)),
+            None => join,
+        }
+    }
+
+    /// Statements for the blocks from `next` on, up to `stop`.
+    fn seq(&mut self, mut next: Option<i128>, stop: Option<i128>) -> Vec<Stmt> {
+        let mut out = Vec::new();
+        while let Some(start) = next {
+            if next == stop {
+                break;
+            }
+            if let Some(jump) = self.loop_jump(start) {
+                out.push(jump);
+                break;
+            }
+            if self.done.contains(&start) || !self.lowered.contains_key(&start) {
+                out.push(Stmt::Goto(start));
+                break;
+            }
+            if self.loops.contains_key(&start) && self.stack.last() != Some(&start) {
+                next = self.structure_loop(start, &mut out);
+                continue;
+            }
+            self.done.insert(start);
+            out.push(Stmt::Label(start));
+            let (stmts, term) = self.lowered[&start].clone();
+            out.extend(stmts);
+            next = match term {
+                Term::Goto(to) => Some(to),
+                Term::End => None,
+                Term::Branch { cond, then, otherwise } => {
+                    let join = self.join(start);
+                    let then = self.seq(Some(then), join);
+                    let otherwise = self.seq(Some(otherwise), join);
+                    out.push(Stmt::If { cond, then, otherwise });
+                    join
+                }
+            };
+        }
+        out
+    }
+
+    /// Pushes the loop headed by `header`, a `while` if the header only tests whether to
+    /// leave, and gives where control goes after it.
+    fn structure_loop(&mut self, header: i128, out: &mut Vec<Stmt>) -> Option<i128> {
+        self.stack.push(header);
+        let follow = self.loops[&header].follow;
+        let (stmts, term) = self.lowered[&header].clone();
+        let test = match term {
+            Term::Branch { cond, then, otherwise } if stmts.is_empty() => {
+                let body = &self.loops[&header].body;
+                if follow == Some(otherwise) && body.contains(&then) {
+                    Some((cond, then))
+                } else if follow == Some(then) && body.contains(&otherwise) {
+                    Some((negate(cond), otherwise))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let stmt = match test {
+            Some((cond, inside)) => {
+                self.done.insert(header);
+                out.push(Stmt::Label(header));
+                Stmt::Loop { cond: Some(cond), body: self.seq(Some(inside), None) }
+            }
+            None => Stmt::Loop { cond: None, body: self.seq(Some(header), None) },
+        };
+        out.push(stmt);
+        self.stack.pop();
+        follow
+    }
+}
+
+fn gotos(stmts: &[Stmt], targets: &mut BTreeSet<i128>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Goto(target) => {
+                targets.insert(*target);
+            }
+            Stmt::If { then, otherwise, .. } => {
+                gotos(then, targets);
+                gotos(otherwise, targets);
+            }
+            Stmt::Loop { body, .. } => gotos(body, targets),
+            _ => {}
+        }
+    }
+}
+
+fn drop_labels(stmts: &mut Vec<Stmt>, targets: &BTreeSet<i128>) {
+    stmts.retain(|stmt| !matches!(stmt, Stmt::Label(start) if !targets.contains(start)));
+    for stmt in stmts {
+        match stmt {
+            Stmt::If { then, otherwise, .. } => {
+                drop_labels(then, targets);
+                drop_labels(otherwise, targets);
+            }
+            Stmt::Loop { body, .. } => drop_labels(body, targets),
+            _ => {}
+        }
+    }
+}
+
+/// Drops a `continue` that ends a loop body anyway.
+fn strip_continue(body: &mut Vec<Stmt>) {
+    match body.last_mut() {
+        Some(Stmt::Continue) => {
+            body.pop();
+        }
+        Some(Stmt::If { then, otherwise, .. }) => {
+            strip_continue(then);
+            strip_continue(otherwise);
+        }
+        _ => {}
+    }
+}
+
+/// Drops empty branches, and moves an `else` after its `if` when the `then` side never
+/// gets to the end.
+fn tidy(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut out = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::If { cond, then, otherwise } => {
+                let (then, otherwise) = (tidy(then), tidy(otherwise));
+                if then.is_empty() && otherwise.is_empty() {
+                    continue;
+                } else if then.is_empty() {
+                    out.push(Stmt::If { cond: negate(cond), then: otherwise, otherwise: Vec::new() });
+                } else if !otherwise.is_empty() && then.last().unwrap().is_jump() {
+                    out.push(Stmt::If { cond, then, otherwise: Vec::new() });
+                    out.extend(otherwise);
+                } else {
+                    out.push(Stmt::If { cond, then, otherwise });
+                }
+            }
+            Stmt::Loop { cond, mut body } => {
+                strip_continue(&mut body);
+                out.push(Stmt::Loop { cond, body: tidy(body) });
+            }
+            stmt => out.push(stmt),
+        }
+    }
+    out
+}
+
+fn write_stmts(out: &mut String, stmts: &[Stmt], depth: usize) {
+    let indent = "    ".repeat(depth);
+    for stmt in stmts {
+        let written = match stmt {
+            Stmt::Assign { dest, value, patches: Some(start) } =>
+                writeln!(out, "{}{} = {}  // patches the instruction at {}", indent, dest, value, start),
+            Stmt::Assign { dest, value, .. } => writeln!(out, "{}{} = {}", indent, dest, value),
+            Stmt::Output(value) => writeln!(out, "{}output({})", indent, value),
+            Stmt::Call { result, callee, args } => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                let result = result.as_ref().map_or(String::new(), |r| format!("{} = ", r));
+                writeln!(out, "{}{}{}({})", indent, result, callee, args.join(", "))
+            }
+            Stmt::Base(Expr::Num(k)) if *k < 0 => writeln!(out, "{}rb -= {}", indent, -k),
+            Stmt::Base(value) => writeln!(out, "{}rb += {}", indent, value),
+            Stmt::If { cond, then, otherwise } => {
+                writeln!(out, "{}if {} {{", indent, cond).unwrap();
+                let (mut then, mut otherwise) = (then, otherwise);
+                loop {
+                    write_stmts(out, then, depth + 1);
+                    match &otherwise[..] {
+                        [] => break,
+                        [Stmt::If { cond, then: t, otherwise: o }] => {
+                            writeln!(out, "{}}} else if {} {{", indent, cond).unwrap();
+                            then = t;
+                            otherwise = o;
+                        }
+                        _ => {
+                            writeln!(out, "{}}} else {{", indent).unwrap();
+                            write_stmts(out, otherwise, depth + 1);
+                            break;
+                        }
+                    }
+                }
+                writeln!(out, "{}}}", indent)
+            }
+            Stmt::Loop { cond, body } => {
+                match cond {
+                    Some(cond) => writeln!(out, "{}while {} {{", indent, cond).unwrap(),
+                    None => writeln!(out, "{}loop {{", indent).unwrap(),
+                }
+                write_stmts(out, body, depth + 1);
+                writeln!(out, "{}}}", indent)
+            }
+            Stmt::Label(start) => writeln!(out, "{}L{}:", indent, start),
+            Stmt::Goto(start) => writeln!(out, "{}goto L{}", indent, start),
+            Stmt::GotoComputed(target) => writeln!(out, "{}goto *{}", indent, target),
+            Stmt::Break => writeln!(out, "{}break", indent),
+            Stmt::Continue => writeln!(out, "{}continue", indent),
+            Stmt::Return(Some(result)) => writeln!(out, "{}return {}", indent, result),
+            Stmt::Return(None) => writeln!(out, "{}return", indent),
+            Stmt::Halt => writeln!(out, "{}halt", indent),
+        };
+        written.unwrap();
+    }
+}
+
+/// Lifts a program into readable pseudocode, the main program at 0 first and then one
+/// `fn` per call target found by `Cfg::build`.
+///
+/// Functions are expected to follow the usual calling convention: the caller stores the
+/// return address at `rb+0` and the arguments from `rb+1` on, the function reserves its
+/// frame with `arb #n` and hands back its result in its first argument. Cells in the
+/// frame are named `a1...` for arguments and `l1...` for locals, cells past it `t1...`,
+/// and other cells by address, like `m1024`. A comparison feeding straight into a jump
+/// becomes the condition, branches become `if` and back edges `loop` or `while`, and
+/// anything that does not nest is left as `goto`.
+///
+/// ```ignore
+/// print!("{}", decompile(&program));
+/// ```
+pub fn decompile(program: &Memory) -> String {
+    let cfg = Cfg::build(program);
+    let decompiler = Decompiler::new(&cfg);
+    let mut out = String::new();
+    for f in decompiler.functions.values() {
+        if !cfg.blocks.contains_key(&f.entry) {
+            continue;
+        }
+        if !out.is_empty() {
+            out += "\n";
+        }
+        if f.entry == 0 {
+            out += "fn main() {\n";
+        } else {
+            let args: Vec<String> = (1..=f.arity).map(|i| format!("a{}", i)).collect();
+            writeln!(out, "fn f{}({}) {{", f.entry, args.join(", ")).unwrap();
+        }
+        write_stmts(&mut out, &decompiler.function(f), 1);
+        out += "}\n";
+    }
+    out
+}
@@ -0,0 +1,308 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use super::{Memory, parse_instr};
+
+/// A decoded parameter, shown the way the disassembler prints it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// Mode 0, the word at an address: `[12]`.
+    Position(i128),
+    /// Mode 1, the value itself: `#5`.
+    Immediate(i128),
+    /// Mode 2, an offset from the relative base: `rb+3`.
+    Relative(i128),
+}
+
+impl Operand {
+    pub fn mode(&self) -> i128 {
+        match *self {
+            Operand::Position(_) => 0,
+            Operand::Immediate(_) => 1,
+            Operand::Relative(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Position(address) => write!(f, "[{}]", address),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(offset) if offset < 0 => write!(f, "rb{}", offset),
+            Operand::Relative(offset) => write!(f, "rb+{}", offset),
+        }
+    }
+}
+
+/// Mnemonic and parameter count for an opcode, if it is one.
+pub fn opcode_info(opcode: i128) -> Option<(&'static str, usize)> {
+    match opcode {
+        1 => Some(("add", 3)),
+        2 => Some(("mul", 3)),
+        3 => Some(("in", 1)),
+        4 => Some(("out", 1)),
+        5 => Some(("jt", 2)),
+        6 => Some(("jf", 2)),
+        7 => Some(("lt", 3)),
+        8 => Some(("eq", 3)),
+        9 => Some(("arb", 1)),
+        99 => Some(("hlt", 0)),
+        _ => None,
+    }
+}
+
+/// Which parameter, counting from 0, an opcode writes to, if any.
+pub fn write_param(opcode: i128) -> Option<usize> {
+    match opcode {
+        1 | 2 | 7 | 8 => Some(2),
+        3 => Some(0),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: i128,
+    /// The two digit opcode, without the modes.
+    pub opcode: i128,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    /// The raw words the instruction was decoded from.
+    pub words: Vec<i128>,
+}
+
+impl Instruction {
+    /// Number of words, opcode included.
+    pub fn size(&self) -> usize {
+        self.words.len()
+    }
+
+    /// The first word as an assembler would write it, with mode digits only for the parameters.
+    pub fn canonical_opcode(&self) -> i128 {
+        let mut scale = 100;
+        let mut word = self.opcode;
+        for operand in &self.operands {
+            word += operand.mode() * scale;
+            scale *= 10;
+        }
+        word
+    }
+
+    /// Whether control never falls through to the next instruction.
+    pub fn is_unconditional(&self) -> bool {
+        match (self.opcode, self.operands.first()) {
+            (99, _) => true,
+            (5, Some(&Operand::Immediate(x))) => x != 0,
+            (6, Some(&Operand::Immediate(x))) => x == 0,
+            _ => false,
+        }
+    }
+
+    /// Where a jump goes, if that is known without running the program.
+    pub fn jump_target(&self) -> Option<i128> {
+        match (self.opcode, self.operands.get(1)) {
+            (5, Some(&Operand::Immediate(target))) | (6, Some(&Operand::Immediate(target))) => Some(target),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic)?;
+        for (i, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line of a listing: an instruction, or words that are never executed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Instruction(Instruction),
+    Data { address: i128, words: Vec<i128> },
+}
+
+impl Line {
+    pub fn address(&self) -> i128 {
+        match self {
+            Line::Instruction(instr) => instr.address,
+            Line::Data { address, .. } => *address,
+        }
+    }
+
+    pub fn words(&self) -> &[i128] {
+        match self {
+            Line::Instruction(instr) => &instr.words,
+            Line::Data { words, .. } => words,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Line::Instruction(instr) => instr.to_string(),
+            Line::Data { .. } => ".data".to_string(),
+        };
+        let words: Vec<String> = self.words().iter().map(|w| w.to_string()).collect();
+        write!(f, "{:>6}: {:<28} {}", self.address(), text, words.join(","))
+    }
+}
+
+/// Decodes the instruction at `address`, or `None` if the word there is not a valid instruction.
+pub fn decode(ram: &Memory, address: i128) -> Option<Instruction> {
+    let raw = ram.get(address);
+    if raw <= 0 {
+        return None;
+    }
+    let (opcode, m1, m2, m3) = parse_instr(raw);
+    let (mnemonic, count) = opcode_info(opcode)?;
+    let modes = [m1, m2, m3];
+    let mut operands = Vec::new();
+    let mut words = vec![raw];
+    for (i, &mode) in modes[..count].iter().enumerate() {
+        let word = ram.get(address.checked_add(1 + i as i128)?);
+        words.push(word);
+        operands.push(match mode {
+            0 => Operand::Position(word),
+            1 => Operand::Immediate(word),
+            2 => Operand::Relative(word),
+            _ => return None,
+        });
+    }
+    Some(Instruction { address, opcode, mnemonic, operands, words })
+}
+
+/// Addresses of every instruction reachable from address 0.
+///
+/// Control is followed through fall-through and jumps with an immediate target. Jumps through
+/// memory cannot be followed, but the usual call idiom is: after `jt #1, #f`, the next address
+/// counts as reachable if it also shows up as an immediate operand somewhere reachable, since
+/// that is how a return address gets pushed.
+pub fn reachable(ram: &Memory) -> BTreeSet<i128> {
+    reachable_from(ram, &[0])
+}
+
+/// Like `reachable`, but starting from every address in `entries`, such as ones seen executing.
+pub fn reachable_from(ram: &Memory, entries: &[i128]) -> BTreeSet<i128> {
+    reachable_code(ram, entries).0
+}
+
+/// Like `reachable_from`, also giving the addresses control reaches that do not hold a
+/// valid instruction. Only ones reached without guessing return addresses count, since
+/// code found from a wrong guess is really data.
+pub fn reachable_code(ram: &Memory, entries: &[i128]) -> (BTreeSet<i128>, BTreeSet<i128>) {
+    let mut code = BTreeSet::new();
+    let mut dead_ends = BTreeSet::new();
+    let mut guessing = false;
+    let mut tried = HashSet::new();
+    let mut immediates = HashSet::new();
+    let mut calls = Vec::new();
+    let mut work = entries.to_vec();
+    loop {
+        while let Some(address) = work.pop() {
+            if address < 0 || !tried.insert(address) {
+                continue;
+            }
+            let instr = match decode(ram, address) {
+                Some(instr) => instr,
+                None => {
+                    if !guessing {
+                        dead_ends.insert(address);
+                    }
+                    continue;
+                }
+            };
+            code.insert(address);
+            for operand in &instr.operands {
+                if let Operand::Immediate(value) = *operand {
+                    immediates.insert(value);
+                }
+            }
+            if let Some(target) = instr.jump_target() {
+                work.push(target);
+            }
+            let next = address + instr.size() as i128;
+            if !instr.is_unconditional() {
+                work.push(next);
+            } else if instr.opcode != 99 {
+                calls.push(next);
+            }
+        }
+        let returns: Vec<i128> = calls.iter()
+            .cloned()
+            .filter(|next| immediates.contains(next) && !tried.contains(next))
+            .collect();
+        if returns.is_empty() {
+            return (code, dead_ends);
+        }
+        guessing = true;
+        work = returns;
+    }
+}
+
+/// Most words put on one `.data` line.
+const DATA_WIDTH: usize = 8;
+
+/// Disassembles the loaded image. Reachable code becomes instructions and everything
+/// else `.data`, with runs of data split into lines of a few words.
+pub fn disassemble(ram: &Memory) -> Vec<Line> {
+    disassemble_from(ram, &[0])
+}
+
+/// Like `disassemble`, with code found from every address in `entries`.
+pub fn disassemble_from(ram: &Memory, entries: &[i128]) -> Vec<Line> {
+    let code = reachable_from(ram, entries);
+    let end = ram.image_len() as i128;
+    let mut lines = Vec::new();
+    let mut data: Vec<i128> = Vec::new();
+    let mut data_start = 0;
+    let mut address = 0;
+    while address < end {
+        let instr = if code.contains(&address) { decode(ram, address) } else { None };
+        match instr {
+            // an instruction that runs off the end of the image is shown as data
+            Some(ref instr) if address + instr.size() as i128 <= end => {
+                if !data.is_empty() {
+                    lines.push(Line::Data { address: data_start, words: data.split_off(0) });
+                }
+                address += instr.size() as i128;
+                lines.push(Line::Instruction(instr.clone()));
+            }
+            _ => {
+                if data.is_empty() {
+                    data_start = address;
+                }
+                data.push(ram.get(address));
+                address += 1;
+                if data.len() == DATA_WIDTH {
+                    lines.push(Line::Data { address: data_start, words: data.split_off(0) });
+                }
+            }
+        }
+    }
+    if !data.is_empty() {
+        lines.push(Line::Data { address: data_start, words: data });
+    }
+    lines
+}
+
+/// A listing as assembler source that assembles back to the same words. An instruction
+/// whose opcode word has stray mode digits is kept as `.data` with the instruction in a comment.
+pub fn to_source(lines: &[Line]) -> String {
+    let mut source = String::new();
+    for line in lines {
+        let words: Vec<String> = line.words().iter().map(|w| w.to_string()).collect();
+        match line {
+            Line::Instruction(instr) if instr.canonical_opcode() == instr.words[0] => {
+                source += &format!("{}\n", instr);
+            }
+            Line::Instruction(instr) => source += &format!(".data {} ; {}\n", words.join(", "), instr),
+            Line::Data { .. } => source += &format!(".data {}\n", words.join(", ")),
+        }
+    }
+    source
+}
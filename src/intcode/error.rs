@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt;
+
+/// A fault raised by the instruction at `ip`. The machine is left as it was
+/// before that instruction, so it can be inspected or patched and resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntcodeError {
+    /// The word at `ip` does not decode to a known opcode.
+    BadOpcode { ip: i128, opcode: i128 },
+    /// A parameter mode digit other than 0, 1 or 2.
+    BadMode { ip: i128, opcode: i128, mode: i128 },
+    /// A read or write below address 0.
+    NegativeAddress { ip: i128, opcode: i128, address: i128 },
+    /// An `add` or `mul` in a checked machine whose result does not fit in a word, or a word
+    /// used as an address, jump target or relative base offset that does not fit in an `i128`.
+    Overflow { ip: i128, opcode: i128 },
+    /// Immediate mode on the parameter an instruction writes to, in a strict machine.
+    ImmediateWrite { ip: i128, opcode: i128 },
+    /// Nonzero mode digits past the instruction's last parameter, in a strict machine.
+    ExtraDigits { ip: i128, opcode: i128 },
+}
+
+impl IntcodeError {
+    pub fn ip(&self) -> i128 {
+        match *self {
+            IntcodeError::BadOpcode { ip, .. } => ip,
+            IntcodeError::BadMode { ip, .. } => ip,
+            IntcodeError::NegativeAddress { ip, .. } => ip,
+            IntcodeError::Overflow { ip, .. } => ip,
+            IntcodeError::ImmediateWrite { ip, .. } => ip,
+            IntcodeError::ExtraDigits { ip, .. } => ip,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntcodeError::BadOpcode { ip, opcode } =>
+                write!(f, "bad opcode {} at ip {}", opcode, ip),
+            IntcodeError::BadMode { ip, opcode, mode } =>
+                write!(f, "bad parameter mode {} in {} at ip {}", mode, opcode, ip),
+            IntcodeError::NegativeAddress { ip, opcode, address } =>
+                write!(f, "negative address {} used by {} at ip {}", address, opcode, ip),
+            IntcodeError::Overflow { ip, opcode } =>
+                write!(f, "overflow in {} at ip {}", opcode, ip),
+            IntcodeError::ImmediateWrite { ip, opcode } =>
+                write!(f, "immediate mode write in {} at ip {}", opcode, ip),
+            IntcodeError::ExtraDigits { ip, opcode } =>
+                write!(f, "mode digits past the last parameter in {} at ip {}", opcode, ip),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
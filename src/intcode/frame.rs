@@ -0,0 +1,159 @@
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+
+use super::{Event, Input, IntcodeError, Machine, Output};
+
+/// The output stream ended part way through a frame, or the machine faulted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// Only `values` came before the stream stopped, short of `arity`. `stop` is why the
+    /// machine stopped, or `None` for a stream that ran out, like a closed channel.
+    Truncated { arity: usize, values: Vec<i128>, stop: Option<Event> },
+    Fault(IntcodeError),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Truncated { arity, values, stop } => {
+                write!(f, "frame cut short after {} of {} values {:?}", values.len(), arity, values)?;
+                match stop {
+                    Some(Event::NeedsInput) => write!(f, ": the machine is waiting for input"),
+                    Some(Event::Halted) => write!(f, ": the machine halted"),
+                    Some(Event::Limit(limit)) => write!(f, ": {}", limit),
+                    Some(Event::Output(_)) | None => write!(f, ": the output ended"),
+                }
+            }
+            FrameError::Fault(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+impl From<IntcodeError> for FrameError {
+    fn from(e: IntcodeError) -> FrameError {
+        FrameError::Fault(e)
+    }
+}
+
+/// Groups a machine's output into frames of `arity` values, each turned into a `T` by
+/// `map`. A machine has to write a whole frame without waiting for input in between.
+///
+/// ```ignore
+/// let mut tiles = Frames::tiles();
+/// while let Event::Output(tile) = tiles.next_frame(&mut arcade)? {
+///     screen.draw(tile);
+/// }
+/// ```
+pub struct Frames<T, F = fn(&[i128]) -> T> {
+    arity: usize,
+    map: F,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, F: FnMut(&[i128]) -> T> Frames<T, F> {
+    pub fn new(arity: usize, map: F) -> Frames<T, F> {
+        assert!(arity > 0, "frames need at least one value");
+        Frames { arity, map, marker: PhantomData }
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Runs the machine until it has written a whole frame, giving `Event::Output` with
+    /// the frame. A machine that stops between frames gives the event it stopped with.
+    pub fn next_frame<I: Input, O: Output>(&mut self, machine: &mut Machine<I, O>) -> Result<Event<T>, FrameError> {
+        let mut values = Vec::with_capacity(self.arity);
+        while values.len() < self.arity {
+            match machine.run_until_event()? {
+                Event::Output(value) => values.push(value),
+                stop if values.is_empty() => return Ok(match stop {
+                    Event::NeedsInput => Event::NeedsInput,
+                    Event::Limit(limit) => Event::Limit(limit),
+                    _ => Event::Halted,
+                }),
+                stop => return Err(FrameError::Truncated { arity: self.arity, values, stop: Some(stop) }),
+            }
+        }
+        Ok(Event::Output((self.map)(&values)))
+    }
+
+    /// Every frame the machine writes before it stops, and the event it stopped with.
+    pub fn until_stop<I: Input, O: Output>(&mut self, machine: &mut Machine<I, O>) -> Result<(Vec<T>, Event), FrameError> {
+        let mut frames = Vec::new();
+        loop {
+            match self.next_frame(machine)? {
+                Event::Output(frame) => frames.push(frame),
+                Event::NeedsInput => return Ok((frames, Event::NeedsInput)),
+                Event::Limit(limit) => return Ok((frames, Event::Limit(limit))),
+                Event::Halted => return Ok((frames, Event::Halted)),
+            }
+        }
+    }
+
+    /// The next frame from a stream of output values, like a channel's `iter()`. Gives
+    /// `None` if the stream ends between frames.
+    pub fn read<V: Iterator<Item = i128>>(&mut self, values: &mut V) -> Result<Option<T>, FrameError> {
+        let frame: Vec<i128> = values.take(self.arity).collect();
+        if frame.is_empty() {
+            Ok(None)
+        } else if frame.len() < self.arity {
+            Err(FrameError::Truncated { arity: self.arity, values: frame, stop: None })
+        } else {
+            Ok(Some((self.map)(&frame)))
+        }
+    }
+}
+
+/// What the hull painting robot (day 11) writes after each reading of the panel under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paint {
+    /// 0 for black and 1 for white.
+    pub color: i128,
+    /// 0 to turn left and 1 to turn right.
+    pub turn: i128,
+}
+
+/// A tile drawn by the arcade cabinet (day 13). The score is sent as the tile at
+/// (-1, 0) with the score as its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: i128,
+    pub y: i128,
+    pub id: i128,
+}
+
+impl Tile {
+    pub fn is_score(&self) -> bool {
+        self.x == -1 && self.y == 0
+    }
+}
+
+/// A packet sent by a network interface (day 23) to address `dest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub dest: i128,
+    pub x: i128,
+    pub y: i128,
+}
+
+impl Frames<Paint> {
+    pub fn paint() -> Frames<Paint> {
+        Frames::new(2, |v| Paint { color: v[0], turn: v[1] })
+    }
+}
+
+impl Frames<Tile> {
+    pub fn tiles() -> Frames<Tile> {
+        Frames::new(3, |v| Tile { x: v[0], y: v[1], id: v[2] })
+    }
+}
+
+impl Frames<Packet> {
+    pub fn packets() -> Frames<Packet> {
+        Frames::new(3, |v| Packet { dest: v[0], x: v[1], y: v[2] })
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{Sender, Receiver};
+
+/// Where a machine's input instructions read from. `W` is the machine's word type.
+pub trait Input<W = i128> {
+    /// Returns the next value, or `None` if there is nothing to read right now.
+    /// A machine that gets `None` stops with `Event::NeedsInput` and retries later.
+    fn read_input(&mut self) -> Option<W>;
+}
+
+/// Where a machine's output instructions write to.
+pub trait Output<W = i128> {
+    fn write_output(&mut self, value: W);
+}
+
+impl<W> Input<W> for VecDeque<W> {
+    fn read_input(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> Output<W> for VecDeque<W> {
+    fn write_output(&mut self, value: W) {
+        self.push_back(value);
+    }
+}
+
+impl<W> Output<W> for Vec<W> {
+    fn write_output(&mut self, value: W) {
+        self.push(value);
+    }
+}
+
+/// Discards all output, for callers that only look at `Event::Output`.
+impl<W> Output<W> for () {
+    fn write_output(&mut self, _value: W) {
+    }
+}
+
+/// Blocks until a value arrives. Only returns `None` once every sender is gone.
+impl<W> Input<W> for Receiver<W> {
+    fn read_input(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+/// Output sent after the receiver has hung up is dropped.
+impl<W> Output<W> for Sender<W> {
+    fn write_output(&mut self, value: W) {
+        let _ = self.send(value);
+    }
+}
+
+impl<W, F: FnMut() -> Option<W>> Input<W> for F {
+    fn read_input(&mut self) -> Option<W> {
+        self()
+    }
+}
+
+impl<W, F: FnMut(W)> Output<W> for F {
+    fn write_output(&mut self, value: W) {
+        self(value)
+    }
+}
+
+/// Reads from a channel without blocking, so an empty channel reads as `None`.
+#[derive(Debug)]
+pub struct NonBlocking<W = i128>(pub Receiver<W>);
+
+impl<W> Input<W> for NonBlocking<W> {
+    fn read_input(&mut self) -> Option<W> {
+        self.0.try_recv().ok()
+    }
+}
+
+/// Feeds the values of an iterator in order.
+#[derive(Debug, Clone)]
+pub struct FromIter<T>(pub T);
+
+impl<W, T: Iterator<Item = W>> Input<W> for FromIter<T> {
+    fn read_input(&mut self) -> Option<W> {
+        self.0.next()
+    }
+}
+
+/// Reads `default` instead of blocking whenever the inner input is empty.
+#[derive(Debug, Clone)]
+pub struct OrDefault<I, W = i128> {
+    pub inner: I,
+    pub default: W,
+}
+
+impl<W: Clone, I: Input<W>> Input<W> for OrDefault<I, W> {
+    fn read_input(&mut self) -> Option<W> {
+        Some(self.inner.read_input().unwrap_or_else(|| self.default.clone()))
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::memory::PAGE_SIZE;
+use super::{Machine, Word, write_address};
+
+/// How far a machine may run. Unset limits do not apply, which is the default.
+///
+/// A machine that reaches a limit stops with `Event::Limit` before the instruction that
+/// would break it, like it does for `Event::NeedsInput`, so it can be inspected or given
+/// more room and resumed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Instructions the machine may have executed, as counted in `Machine::steps`.
+    pub steps: Option<u64>,
+    /// When the machine has to stop by. Only looked at every `TIME_CHECK_INTERVAL` steps.
+    pub deadline: Option<Instant>,
+    /// Highest address that may be written to.
+    pub max_address: Option<i128>,
+    /// Most cells RAM may hold, counting the loaded image and every page allocated beyond it.
+    pub max_cells: Option<usize>,
+}
+
+/// Steps between looks at the clock, since reading it costs more than an instruction.
+pub const TIME_CHECK_INTERVAL: u64 = 1024;
+
+impl Limits {
+    /// A deadline `timeout` from now.
+    pub fn timeout(timeout: Duration) -> Option<Instant> {
+        Some(Instant::now() + timeout)
+    }
+}
+
+/// Which limit stopped a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    Time,
+    /// The next instruction would write to this address, above `Limits::max_address`.
+    Address(i128),
+    /// The next instruction would allocate a page beyond `Limits::max_cells`.
+    Cells,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Limit::Steps => write!(f, "instruction limit reached"),
+            Limit::Time => write!(f, "time limit reached"),
+            Limit::Address(address) => write!(f, "write to [{}] is above the address limit", address),
+            Limit::Cells => write!(f, "memory limit reached"),
+        }
+    }
+}
+
+/// The limit the next instruction would break, if any. An instruction that will fault
+/// anyway is let through to report its error.
+pub(super) fn exceeded<I, O, W: Word>(m: &Machine<I, O, W>) -> Option<Limit> {
+    let limits = &m.limits;
+    if let Some(steps) = limits.steps {
+        if m.steps >= steps {
+            return Some(Limit::Steps);
+        }
+    }
+    if let Some(deadline) = limits.deadline {
+        if m.steps.is_multiple_of(TIME_CHECK_INTERVAL) && Instant::now() >= deadline {
+            return Some(Limit::Time);
+        }
+    }
+    if limits.max_address.is_none() && limits.max_cells.is_none() {
+        return None;
+    }
+    let address = match write_address(m) {
+        Ok(Some(address)) if address >= 0 => address,
+        _ => return None,
+    };
+    if let Some(max_address) = limits.max_address {
+        if address > max_address {
+            return Some(Limit::Address(address));
+        }
+    }
+    if let Some(max_cells) = limits.max_cells {
+        if !m.ram.is_allocated(address) && m.ram.cells() + PAGE_SIZE as usize > max_cells {
+            return Some(Limit::Cells);
+        }
+    }
+    None
+}
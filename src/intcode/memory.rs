@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::Word;
+
+/// Words per lazily allocated page beyond the loaded image.
+pub const PAGE_SIZE: i128 = 1024;
+
+/// Intcode RAM. The loaded image lives in a dense `Vec`, so instruction fetches
+/// are a bounds check and an index. Anything written past the image goes into
+/// fixed size pages allocated on first write. Cells never written read as 0.
+///
+/// Both are shared copy-on-write, so cloning a `Memory` (and forking a machine)
+/// is cheap and a clone only copies the image or a page when it first writes to it.
+///
+/// Negative addresses have no cells: they read as 0 and writes to them are dropped.
+/// The interpreter reports them as errors in `read` and `store` before getting here.
+#[derive(Debug, Clone, Default)]
+pub struct Memory<W = i128> {
+    dense: Arc<Vec<W>>,
+    pages: HashMap<i128, Arc<Vec<W>>>,
+}
+
+impl<W: Word> Memory<W> {
+    pub fn new() -> Memory<W> {
+        Memory { dense: Arc::new(Vec::new()), pages: HashMap::new() }
+    }
+
+    pub fn get(&self, address: i128) -> W {
+        if (address as u128) < self.dense.len() as u128 {
+            return self.dense[address as usize].clone();
+        }
+        if address < 0 {
+            return W::from(0);
+        }
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => page[(address % PAGE_SIZE) as usize].clone(),
+            None => W::from(0),
+        }
+    }
+
+    pub fn set(&mut self, address: i128, value: W) {
+        if (address as u128) < self.dense.len() as u128 {
+            Arc::make_mut(&mut self.dense)[address as usize] = value;
+            return;
+        }
+        if address < 0 {
+            return;
+        }
+        if value.is_zero() && !self.pages.contains_key(&(address / PAGE_SIZE)) {
+            // already reads as 0, no need to allocate
+            return;
+        }
+        let page = self.pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| Arc::new(vec![W::from(0); PAGE_SIZE as usize]));
+        Arc::make_mut(page)[(address % PAGE_SIZE) as usize] = value;
+    }
+
+    /// Number of words in the dense segment, i.e. the size of the loaded image.
+    pub fn image_len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Number of pages allocated beyond the image.
+    pub fn num_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Number of cells held: the image and every allocated page.
+    pub fn cells(&self) -> usize {
+        self.dense.len() + self.pages.len() * PAGE_SIZE as usize
+    }
+
+    /// Whether `address` already has a cell, so writing to it allocates nothing.
+    pub fn is_allocated(&self, address: i128) -> bool {
+        (address as u128) < self.dense.len() as u128 || self.pages.contains_key(&(address / PAGE_SIZE))
+    }
+
+    /// Whether `self` and `other` still share the image, i.e. neither has written to it since cloning.
+    pub fn shares_image_with(&self, other: &Memory<W>) -> bool {
+        Arc::ptr_eq(&self.dense, &other.dense)
+    }
+
+    /// Every non-zero cell, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (i128, W)> + '_ {
+        let mut page_ids: Vec<i128> = self.pages.keys().cloned().collect();
+        page_ids.sort_unstable();
+        let paged = page_ids.into_iter().flat_map(move |id| {
+            self.pages[&id]
+                .iter()
+                .enumerate()
+                .map(move |(i, x)| (id * PAGE_SIZE + i as i128, x.clone()))
+        });
+        self.dense
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i as i128, x.clone()))
+            .chain(paged)
+            .filter(|(_, x)| !x.is_zero())
+    }
+}
+
+impl<W> From<Vec<W>> for Memory<W> {
+    fn from(dense: Vec<W>) -> Memory<W> {
+        Memory { dense: Arc::new(dense), pages: HashMap::new() }
+    }
+}
+
+/// Two memories are equal if every address reads the same, however it is stored.
+impl<W: Word> PartialEq for Memory<W> {
+    fn eq(&self, other: &Memory<W>) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<W: Word> Eq for Memory<W> {}
@@ -0,0 +1,332 @@
+/// A small regular expression, enough for matching program output in scripts.
+///
+/// Supports literal characters, `.` (anything but a newline), classes like `[a-z_]` and
+/// `[^,]`, the escapes `\d`, `\w`, `\s`, `\n` and `\t` (any other escaped character
+/// stands for itself), groups `( )`, alternation `|` and the greedy quantifiers `*`, `+`
+/// and `?`. Groups are numbered from 1 by their opening parenthesis.
+///
+/// It runs as a Pike VM: every way the pattern could match moves through the text together,
+/// one char at a time, in order of preference. So matching takes time linear in the text,
+/// and nothing recurses on its length however much output piles up unmatched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    program: Vec<Inst>,
+    groups: usize,
+}
+
+/// Where a pattern matched, as char offsets into the text. `groups[0]` is the whole match
+/// and a group that did not take part is `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub groups: Vec<Option<(usize, usize)>>,
+}
+
+impl Match {
+    pub fn start(&self) -> usize {
+        self.groups[0].unwrap().0
+    }
+
+    pub fn end(&self) -> usize {
+        self.groups[0].unwrap().1
+    }
+
+    /// The text of group `i`, if it took part in the match.
+    pub fn group(&self, text: &str, i: usize) -> Option<String> {
+        let (start, end) = self.groups.get(i).cloned().flatten()?;
+        Some(text.chars().skip(start).take(end - start).collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    Any,
+    /// Inclusive ranges, and whether the class is negated.
+    Class(Vec<(char, char)>, bool),
+    /// Alternatives, and the group number.
+    Group(Vec<Vec<Node>>, usize),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+impl Node {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Node::Char(x) => *x == c,
+            Node::Any => c != '\n',
+            Node::Class(ranges, negated) => ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated,
+            _ => false,
+        }
+    }
+}
+
+/// One instruction of a compiled pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    /// Takes one char that the node, a `Char`, `Any` or `Class`, matches.
+    Take(Node),
+    /// Carries on at both, preferring the first.
+    Split(usize, usize),
+    Jump(usize),
+    /// Records the position in a capture slot: `2 * group` for its start, one more for its end.
+    Save(usize),
+    Accept,
+}
+
+/// Compiles `node` onto the end of `program`.
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Group(alternatives, i) => {
+            program.push(Inst::Save(2 * i));
+            let mut jumps = Vec::new();
+            for (k, alternative) in alternatives.iter().enumerate() {
+                let last = k + 1 == alternatives.len();
+                let split = program.len();
+                if !last {
+                    program.push(Inst::Split(split + 1, 0));
+                }
+                for node in alternative {
+                    compile(node, program);
+                }
+                if !last {
+                    jumps.push(program.len());
+                    program.push(Inst::Jump(0));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+            }
+            for jump in jumps {
+                program[jump] = Inst::Jump(program.len());
+            }
+            program.push(Inst::Save(2 * i + 1));
+        }
+        Node::Repeat(inner, min, None) => {
+            for _ in 0..*min {
+                compile(inner, program);
+            }
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile(inner, program);
+            program.push(Inst::Jump(split));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+        Node::Repeat(inner, min, Some(max)) => {
+            for _ in 0..*min {
+                compile(inner, program);
+            }
+            // each optional copy is only tried if the one before it matched
+            let mut splits = Vec::new();
+            for _ in *min..*max {
+                splits.push(program.len());
+                program.push(Inst::Split(0, 0));
+                compile(inner, program);
+            }
+            for split in splits {
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+        }
+        node => program.push(Inst::Take(node.clone())),
+    }
+}
+
+/// The threads waiting at one position, most preferred first, with their capture slots.
+struct Threads {
+    list: Vec<(usize, Vec<Option<usize>>)>,
+    /// Instructions already reached at this position. A thread arriving later would only
+    /// repeat what an earlier, preferred one does, like a repetition that matched nothing.
+    seen: Vec<bool>,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                _ => {
+                    nodes.push(atom);
+                    continue;
+                }
+            };
+            self.pos += 1;
+            nodes.push(Node::Repeat(Box::new(atom), min, max));
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().unwrap();
+        self.pos += 1;
+        match c {
+            '.' => Ok(Node::Any),
+            '(' => {
+                self.groups += 1;
+                let group = self.groups;
+                let alternatives = self.alternatives()?;
+                if self.peek() != Some(')') {
+                    return Err("unclosed '('".to_string());
+                }
+                self.pos += 1;
+                Ok(Node::Group(alternatives, group))
+            }
+            '[' => self.class(),
+            '\\' => self.escape().map(|ranges| match ranges {
+                (ranges, false) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => Node::Char(ranges[0].0),
+                (ranges, negated) => Node::Class(ranges, negated),
+            }),
+            '*' | '+' | '?' => Err(format!("nothing to repeat before '{}'", c)),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    /// After a backslash: the ranges it stands for.
+    fn escape(&mut self) -> Result<(Vec<(char, char)>, bool), String> {
+        let c = self.peek().ok_or_else(|| "trailing '\\'".to_string())?;
+        self.pos += 1;
+        Ok(match c {
+            'd' => (vec![('0', '9')], false),
+            'w' => (vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], false),
+            's' => (vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], false),
+            'n' => (vec![('\n', '\n')], false),
+            't' => (vec![('\t', '\t')], false),
+            c => (vec![(c, c)], false),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        loop {
+            let c = self.peek().ok_or_else(|| "unclosed '['".to_string())?;
+            self.pos += 1;
+            match c {
+                ']' => return Ok(Node::Class(ranges, negated)),
+                '\\' => ranges.extend(self.escape()?.0),
+                c if self.peek() == Some('-') && matches!(self.chars.get(self.pos + 1), Some(&d) if d != ']') => {
+                    let hi = self.chars[self.pos + 1];
+                    self.pos += 2;
+                    ranges.push((c, hi));
+                }
+                c => ranges.push((c, c)),
+            }
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Result<Pattern, String> {
+        let mut p = Parser { chars: source.chars().collect(), pos: 0, groups: 0 };
+        let alternatives = p.alternatives()?;
+        if p.pos < p.chars.len() {
+            return Err("unmatched ')'".to_string());
+        }
+        // the whole expression is group 0
+        let mut program = Vec::new();
+        compile(&Node::Group(alternatives, 0), &mut program);
+        program.push(Inst::Accept);
+        Ok(Pattern { program, groups: p.groups })
+    }
+
+    /// The leftmost match in `text`. Quantifiers take as much as they can.
+    pub fn find(&self, text: &str) -> Option<Match> {
+        let chars: Vec<char> = text.chars().collect();
+        self.find_chars(&chars, 0)
+    }
+
+    /// Every match in `text`, left to right and not overlapping.
+    pub fn find_all(&self, text: &str) -> Vec<Match> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while let Some(m) = self.find_chars(&chars, from) {
+            // step past an empty match so the search moves on
+            from = if m.end() > m.start() { m.end() } else { m.end() + 1 };
+            matches.push(m);
+        }
+        matches
+    }
+
+    fn find_chars(&self, text: &[char], from: usize) -> Option<Match> {
+        let threads = || Threads { list: Vec::new(), seen: vec![false; self.program.len()] };
+        let (mut current, mut next) = (threads(), threads());
+        let mut found = None;
+        for pos in from..=text.len() {
+            // a match starting here is less preferred than any that started earlier
+            if found.is_none() {
+                self.add(&mut current, 0, pos, vec![None; 2 * self.groups + 2]);
+            }
+            for (pc, slots) in current.list.drain(..) {
+                match &self.program[pc] {
+                    Inst::Take(node) => {
+                        if pos < text.len() && node.matches(text[pos]) {
+                            self.add(&mut next, pc + 1, pos + 1, slots);
+                        }
+                    }
+                    // the threads after this one are less preferred, so they are dropped
+                    _ => {
+                        found = Some(slots);
+                        break;
+                    }
+                }
+            }
+            current.seen.iter_mut().for_each(|seen| *seen = false);
+            std::mem::swap(&mut current, &mut next);
+            if found.is_some() && current.list.is_empty() {
+                break;
+            }
+        }
+        let slots = found?;
+        let groups = slots.chunks(2).map(|slot| match *slot {
+            [Some(start), Some(end)] => Some((start, end)),
+            _ => None,
+        });
+        Some(Match { groups: groups.collect() })
+    }
+
+    /// Adds a thread at `pc`, following jumps, splits and saves to the instructions that take
+    /// a char or accept. This recurses on the pattern only, never on the text.
+    fn add(&self, threads: &mut Threads, pc: usize, pos: usize, mut slots: Vec<Option<usize>>) {
+        if threads.seen[pc] {
+            return;
+        }
+        threads.seen[pc] = true;
+        match self.program[pc] {
+            Inst::Jump(to) => self.add(threads, to, pos, slots),
+            Inst::Split(first, second) => {
+                self.add(threads, first, pos, slots.clone());
+                self.add(threads, second, pos, slots);
+            }
+            Inst::Save(slot) => {
+                slots[slot] = Some(pos);
+                self.add(threads, pc + 1, pos, slots);
+            }
+            _ => threads.list.push((pc, slots)),
+        }
+    }
+}
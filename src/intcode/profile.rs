@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::disasm::{decode, opcode_info, write_param};
+use super::word::saturate;
+use super::{Event, Input, IntcodeError, Machine, Memory, Output, Word, get_immediate, get_index, get_instr,
+    parse_instr, run_unprofiled, write_address};
+
+/// Counts of what a machine did while profiling was on. Start one with `Machine::start_profile`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Instructions executed.
+    pub total: u64,
+    /// Executions per instruction address.
+    pub by_address: HashMap<i128, u64>,
+    /// Executions per two digit opcode.
+    pub by_opcode: BTreeMap<i128, u64>,
+    /// Per `jt`/`jf` address, how often it jumped and how often it fell through.
+    pub branches: HashMap<i128, (u64, u64)>,
+    /// Parameter reads per memory cell. Instruction fetches are not counted.
+    pub reads: HashMap<i128, u64>,
+    pub writes: HashMap<i128, u64>,
+    /// Instructions executed under each call stack, as frame entry addresses from the outermost.
+    pub stacks: HashMap<Vec<i128>, u64>,
+    /// The current call stack: where each frame was entered and the base before it.
+    frames: Vec<i128>,
+    frame_bases: Vec<i128>,
+}
+
+impl Profile {
+    fn count_stack(&mut self) {
+        match self.stacks.get_mut(self.frames.as_slice()) {
+            Some(count) => *count += 1,
+            None => {
+                self.stacks.insert(self.frames.clone(), 1);
+            }
+        }
+    }
+
+    /// Charges the instruction at `ip` to the call stack. Intcode has no calls, so frames are
+    /// guessed from the relative base: an `arb` that raises it opens a frame named by its
+    /// own address, and one that lowers it below where a frame started closes that frame.
+    fn count_frame(&mut self, ip: i128, old_base: i128, new_base: i128) {
+        if new_base > old_base {
+            self.frames.push(ip);
+            self.frame_bases.push(old_base);
+            self.count_stack();
+        } else {
+            self.count_stack();
+            while let Some(&frame_base) = self.frame_bases.last() {
+                if frame_base < new_base {
+                    break;
+                }
+                self.frames.pop();
+                self.frame_bases.pop();
+            }
+        }
+    }
+
+    /// A plain text report: totals per opcode, then the `top` hottest addresses,
+    /// branches and memory cells, most expensive first. `program` is used to
+    /// disassemble the hot addresses.
+    pub fn report(&self, program: &Memory, top: usize) -> String {
+        let percent = |n: u64| 100.0 * n as f64 / self.total.max(1) as f64;
+        let mut out = format!("{} instructions executed\n", self.total);
+
+        out += "\nby opcode:\n";
+        let mut opcodes: Vec<(&i128, &u64)> = self.by_opcode.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (&opcode, &count) in opcodes {
+            let name = opcode_info(opcode).map(|(name, _)| name).unwrap_or("???");
+            out += &format!("  {:<4} {:>12} {:>6.2}%\n", name, count, percent(count));
+        }
+
+        out += "\nhot spots:\n";
+        for (address, count) in sorted(&self.by_address, top) {
+            let text = decode(program, address).map(|i| i.to_string()).unwrap_or_default();
+            out += &format!("  {:>6}: {:>12} {:>6.2}%  {}\n", address, count, percent(count), text);
+        }
+
+        out += "\nbranches (taken / not taken):\n";
+        let mut branches: Vec<(i128, (u64, u64))> = self.branches.iter().map(|(&a, &c)| (a, c)).collect();
+        branches.sort_by(|a, b| (b.1 .0 + b.1 .1).cmp(&(a.1 .0 + a.1 .1)).then(a.0.cmp(&b.0)));
+        for (address, (taken, not_taken)) in branches.into_iter().take(top) {
+            out += &format!("  {:>6}: {:>12} / {}\n", address, taken, not_taken);
+        }
+
+        out += "\nhot cells (reads / writes):\n";
+        let mut cells: HashMap<i128, u64> = self.reads.clone();
+        for (&address, &count) in &self.writes {
+            *cells.entry(address).or_insert(0) += count;
+        }
+        for (address, _) in sorted(&cells, top) {
+            let reads = self.reads.get(&address).cloned().unwrap_or(0);
+            let writes = self.writes.get(&address).cloned().unwrap_or(0);
+            out += &format!("  [{}]: {} / {}\n", address, reads, writes);
+        }
+        out
+    }
+
+    /// One line per call stack, `main;f1424;f1984 count`, as read by flamegraph tools.
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self.stacks.iter().map(|(frames, count)| {
+            let mut names = vec!["main".to_string()];
+            names.extend(frames.iter().map(|ip| format!("f{}", ip)));
+            format!("{} {}", names.join(";"), count)
+        }).collect();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+/// The `top` largest counts, largest first and then by address.
+fn sorted(counts: &HashMap<i128, u64>, top: usize) -> Vec<(i128, u64)> {
+    let mut counts: Vec<(i128, u64)> = counts.iter().map(|(&a, &c)| (a, c)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(top);
+    counts
+}
+
+impl<I, O, W> Machine<I, O, W> {
+    /// Counts everything executed from now on, until `take_profile`.
+    pub fn start_profile(&mut self) {
+        self.profile = Some(Profile::default());
+    }
+
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+}
+
+/// Runs one instruction and counts it. What it reads, writes and whether it jumps
+/// are worked out before it runs, the way the instruction itself does.
+pub(super) fn profiled_step<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let ip = m.ip;
+    let base = m.base;
+    let (opcode, m1, m2, m3) = parse_instr(get_instr(m)?);
+    let count = opcode_info(opcode).map(|(_, count)| count).unwrap_or(0);
+    let written = write_param(opcode);
+    let mut reads = Vec::new();
+    for (i, &mode) in [m1, m2, m3][..count].iter().enumerate() {
+        // an operand or address that does not fit faults the instruction, which is then not counted
+        let word = match get_immediate(m, i as i128 + 1) {
+            Ok(word) => saturate(&word),
+            Err(_) => break,
+        };
+        match mode {
+            _ if Some(i) == written => {}
+            0 => reads.push(word),
+            2 => reads.extend(word.checked_add(base)),
+            _ => {}
+        }
+    }
+    let write = write_address(m).ok().flatten();
+    let jumps = match opcode {
+        5 => get_index(m, 1, m1).ok().map(|x: W| !x.is_zero()),
+        6 => get_index(m, 1, m1).ok().map(|x: W| x.is_zero()),
+        _ => None,
+    };
+
+    let event = run_unprofiled(m)?;
+    if event == Some(Event::NeedsInput) {
+        return Ok(event);
+    }
+    let profile = match m.profile.as_mut() {
+        Some(profile) => profile,
+        None => return Ok(event),
+    };
+    profile.total += 1;
+    *profile.by_address.entry(ip).or_insert(0) += 1;
+    *profile.by_opcode.entry(opcode).or_insert(0) += 1;
+    if let Some(jumped) = jumps {
+        let branch = profile.branches.entry(ip).or_insert((0, 0));
+        if jumped { branch.0 += 1 } else { branch.1 += 1 }
+    }
+    for address in reads {
+        *profile.reads.entry(address).or_insert(0) += 1;
+    }
+    if let Some(address) = write {
+        *profile.writes.entry(address).or_insert(0) += 1;
+    }
+    profile.count_frame(ip, base, m.base);
+    Ok(event)
+}
@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::iter;
+use std::time::Duration;
+
+use super::pattern::Pattern;
+use super::{Event, Input, Limit, Limits, Machine, Output};
+
+/// Milliseconds an `expect` waits unless the script sets its own `timeout`.
+pub const DEFAULT_TIMEOUT_MS: u64 = 10_000;
+
+/// A script error at `line` and `col`, both counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl Error for ScriptError {}
+
+fn error<T>(line: usize, col: usize, message: String) -> Result<T, ScriptError> {
+    Err(ScriptError { line, col, message })
+}
+
+/// Why a script stopped early, at the statement on `line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptFailure {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ScriptFailure {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(u64),
+    Str(Text),
+    Var(String),
+    Regex(String),
+    Punct(char),
+    /// A newline or `;`.
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Var(String),
+}
+
+/// A string with `$name` references, filled in when the statement runs.
+#[derive(Debug, Clone, PartialEq)]
+struct Text(Vec<Part>);
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for part in &self.0 {
+            match part {
+                Part::Literal(s) => write!(f, "{}", s.escape_debug())?,
+                Part::Var(name) => write!(f, "${{{}}}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Splits the script into tokens paired with their line and column, dropping `#` comments.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize, usize)>, ScriptError> {
+    let mut tokens = Vec::new();
+    for (n, text) in source.lines().enumerate() {
+        let line = n + 1;
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let col = i + 1;
+            if c == '#' {
+                break;
+            } else if c.is_whitespace() {
+                i += 1;
+            } else if c == ';' {
+                tokens.push((Token::End, line, col));
+                i += 1;
+            } else if c == '{' || c == '}' {
+                tokens.push((Token::Punct(c), line, col));
+                i += 1;
+            } else if c.is_ascii_digit() {
+                let start = i;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.parse() {
+                    Ok(n) => tokens.push((Token::Number(n), line, col)),
+                    Err(_) => return error(line, col, format!("bad number '{}'", word)),
+                }
+            } else if is_name_char(c) {
+                let start = i;
+                while i < chars.len() && is_name_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push((Token::Word(chars[start..i].iter().collect()), line, col));
+            } else if c == '$' {
+                let (name, end) = var_name(&chars, i + 1).ok_or(ScriptError {
+                    line,
+                    col,
+                    message: "expected a variable name after '$'".to_string(),
+                })?;
+                tokens.push((Token::Var(name), line, col));
+                i = end;
+            } else if c == '"' {
+                let (text, end) = string(&chars, i + 1, line)?;
+                tokens.push((Token::Str(text), line, col));
+                i = end;
+            } else if c == '/' {
+                let mut source = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return error(line, col, "unterminated pattern".to_string()),
+                        Some('/') => break,
+                        Some('\\') if chars.get(i + 1) == Some(&'/') => {
+                            source.push('/');
+                            i += 2;
+                        }
+                        Some('\\') if i + 1 < chars.len() => {
+                            source.push('\\');
+                            source.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            source.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                tokens.push((Token::Regex(source), line, col));
+            } else {
+                return error(line, col, format!("unexpected character '{}'", c));
+            }
+        }
+        tokens.push((Token::End, line, chars.len() + 1));
+    }
+    Ok(tokens)
+}
+
+/// The name after a `$` starting at `i`, either bare or in braces, and where it ends.
+fn var_name(chars: &[char], i: usize) -> Option<(String, usize)> {
+    if chars.get(i) == Some(&'{') {
+        let close = (i + 1..chars.len()).find(|&j| chars[j] == '}')?;
+        let name: String = chars[i + 1..close].iter().collect();
+        return if name.is_empty() { None } else { Some((name, close + 1)) };
+    }
+    let end = (i..chars.len()).find(|&j| !is_name_char(chars[j])).unwrap_or(chars.len());
+    if end == i {
+        return None;
+    }
+    Some((chars[i..end].iter().collect(), end))
+}
+
+/// The string whose opening quote is just before `i`, and where it ends.
+fn string(chars: &[char], mut i: usize, line: usize) -> Result<(Text, usize), ScriptError> {
+    let start = i;
+    let mut parts = Vec::new();
+    let mut s = String::new();
+    loop {
+        match chars.get(i) {
+            None => return error(line, start, "unterminated string".to_string()),
+            Some('"') => break,
+            Some('\\') => {
+                let escaped = match chars.get(i + 1) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('\\') => '\\',
+                    Some('"') => '"',
+                    Some('$') => '$',
+                    _ => return error(line, i + 1, "unknown escape".to_string()),
+                };
+                s.push(escaped);
+                i += 2;
+            }
+            Some('$') => {
+                let (name, end) = var_name(chars, i + 1).ok_or(ScriptError {
+                    line,
+                    col: i + 1,
+                    message: "expected a variable name after '$'".to_string(),
+                })?;
+                if !s.is_empty() {
+                    parts.push(Part::Literal(s.split_off(0)));
+                }
+                parts.push(Part::Var(name));
+                i = end;
+            }
+            Some(&c) => {
+                s.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !s.is_empty() || parts.is_empty() {
+        parts.push(Part::Literal(s));
+    }
+    Ok((Text(parts), i + 1))
+}
+
+#[derive(Debug, Clone)]
+enum Expected {
+    Text(Text),
+    /// The pattern, and its source for messages.
+    Pattern(Pattern, String),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Text(text) => write!(f, "\"{}\"", text),
+            Expected::Pattern(_, source) => write!(f, "/{}/", source),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Command {
+    Expect(Expected, Option<String>),
+    Send(Text),
+    Set(String, Text),
+    Print(Text),
+    /// Milliseconds, or none for no timeout.
+    Timeout(Option<u64>),
+    Repeat(Text, Vec<Statement>),
+    For(String, Text, Option<Pattern>, Vec<Statement>),
+}
+
+#[derive(Debug, Clone)]
+struct Statement {
+    line: usize,
+    command: Command,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    /// Where the next token is, or the end of the last line.
+    fn at(&self) -> (usize, usize) {
+        match self.tokens.get(self.pos).or_else(|| self.tokens.last()) {
+            Some(&(_, line, col)) => (line, col),
+            None => (1, 1),
+        }
+    }
+
+    fn fail<T>(&self, message: String) -> Result<T, ScriptError> {
+        let (line, col) = self.at();
+        error(line, col, message)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn skip_ends(&mut self) {
+        while self.peek() == Some(&Token::End) {
+            self.pos += 1;
+        }
+    }
+
+    fn name(&mut self, what: &str) -> Result<String, ScriptError> {
+        match self.peek() {
+            Some(Token::Word(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => self.fail(format!("expected {}", what)),
+        }
+    }
+
+    /// A string, `$name` or number.
+    fn value(&mut self) -> Result<Text, ScriptError> {
+        let text = match self.peek() {
+            Some(Token::Str(text)) => text.clone(),
+            Some(Token::Var(name)) => Text(vec![Part::Var(name.clone())]),
+            Some(Token::Number(n)) => Text(vec![Part::Literal(n.to_string())]),
+            _ => return self.fail("expected a string, variable or number".to_string()),
+        };
+        self.pos += 1;
+        Ok(text)
+    }
+
+    fn pattern(&mut self, source: String) -> Result<Pattern, ScriptError> {
+        let (line, col) = self.at();
+        self.pos += 1;
+        Pattern::new(&source).or_else(|message| error(line, col, format!("bad pattern: {}", message)))
+    }
+
+    /// Statements up to the end of the script, or a `}` if `nested`.
+    fn statements(&mut self, nested: bool) -> Result<Vec<Statement>, ScriptError> {
+        let mut statements = Vec::new();
+        loop {
+            self.skip_ends();
+            match self.peek() {
+                None if nested => return self.fail("missing '}'".to_string()),
+                None => return Ok(statements),
+                Some(Token::Punct('}')) if nested => {
+                    self.pos += 1;
+                    return Ok(statements);
+                }
+                _ => statements.push(self.statement()?),
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, ScriptError> {
+        if self.next() != Some(Token::Punct('{')) {
+            self.pos -= 1;
+            return self.fail("expected '{'".to_string());
+        }
+        self.statements(true)
+    }
+
+    fn statement(&mut self) -> Result<Statement, ScriptError> {
+        let (line, _) = self.at();
+        let keyword = self.name("a command")?;
+        let command = match keyword.as_str() {
+            "expect" => {
+                let expected = match self.peek() {
+                    Some(Token::Regex(source)) => {
+                        let source = source.clone();
+                        Expected::Pattern(self.pattern(source.clone())?, source)
+                    }
+                    _ => Expected::Text(self.value()?),
+                };
+                let capture = match self.peek() {
+                    Some(Token::Word(w)) if w == "capture" => {
+                        self.pos += 1;
+                        Some(self.name("a variable name")?)
+                    }
+                    _ => None,
+                };
+                Command::Expect(expected, capture)
+            }
+            "send" => Command::Send(self.value()?),
+            "set" => {
+                let name = self.name("a variable name")?;
+                Command::Set(name, self.value()?)
+            }
+            "print" => Command::Print(self.value()?),
+            "timeout" => match self.next() {
+                Some(Token::Number(0)) => Command::Timeout(None),
+                Some(Token::Number(ms)) => Command::Timeout(Some(ms)),
+                _ => {
+                    self.pos -= 1;
+                    return self.fail("expected milliseconds".to_string());
+                }
+            },
+            "repeat" => {
+                let count = self.value()?;
+                Command::Repeat(count, self.block()?)
+            }
+            "for" => {
+                let name = self.name("a variable name")?;
+                if self.name("'in'")? != "in" {
+                    self.pos -= 1;
+                    return self.fail("expected 'in'".to_string());
+                }
+                let list = self.value()?;
+                let pattern = match self.peek() {
+                    Some(Token::Regex(source)) => Some(self.pattern(source.clone())?),
+                    _ => None,
+                };
+                Command::For(name, list, pattern, self.block()?)
+            }
+            _ => {
+                self.pos -= 1;
+                return self.fail(format!("unknown command '{}'", keyword));
+            }
+        };
+        match self.peek() {
+            None | Some(Token::End) | Some(Token::Punct('}')) => Ok(Statement { line, command }),
+            _ => self.fail("expected the end of the command".to_string()),
+        }
+    }
+}
+
+/// A script that drives a program reading and writing ASCII text, like `Ascii` does by
+/// hand. Commands are separated by newlines or `;`, and `#` starts a comment:
+///
+/// - `expect "text"` runs the program until it prints `text`, and `expect /pattern/` until
+///   its output matches `pattern` (see `Pattern`). Either can end in `capture name` to
+///   store the pattern's first group, or the whole match, in a variable. Output up to the
+///   end of the match is used up, so the next `expect` only looks at what comes after.
+/// - `send value` queues the value and a newline as input.
+/// - `set name value` and `print value` store and report a value.
+/// - `timeout ms` sets how long each later `expect` may wait, with `0` for no limit.
+/// - `repeat count { ... }` runs the block `count` times.
+/// - `for name in value { ... }` runs the block for each non-empty line of the value, and
+///   `for name in value /pattern/ { ... }` for each match of the pattern in it, taking
+///   the first group if there is one.
+///
+/// Values are numbers, `$name` or strings in double quotes, which may use `$name` and
+/// `${name}` and the escapes `\n`, `\t`, `\"`, `\\` and `\$`.
+///
+/// Output values outside ASCII, like the puzzle answers, show up in the text as decimal
+/// numbers on their own line.
+///
+/// ```ignore
+/// let script = Script::parse("expect \"Command?\" ; send \"north\" ; expect /Items here:/")?;
+/// let run = script.run(&mut new_machine(program));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Script {
+    statements: Vec<Statement>,
+}
+
+/// What a script did, whether or not it finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptRun {
+    pub vars: HashMap<String, String>,
+    /// Values passed to `print`, in order.
+    pub printed: Vec<String>,
+    /// Everything the program printed.
+    pub output: String,
+    pub result: Result<(), ScriptFailure>,
+}
+
+impl Script {
+    pub fn parse(source: &str) -> Result<Script, ScriptError> {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+        Ok(Script { statements: parser.statements(false)? })
+    }
+
+    /// Runs the script against `machine`, which is left where the script stopped.
+    pub fn run<I: Input + Extend<i128>, O: Output>(&self, machine: &mut Machine<I, O>) -> ScriptRun {
+        let mut session = Session {
+            machine,
+            vars: HashMap::new(),
+            printed: Vec::new(),
+            output: String::new(),
+            unmatched: String::new(),
+            timeout: Some(DEFAULT_TIMEOUT_MS),
+        };
+        let result = session.execute(&self.statements);
+        ScriptRun { vars: session.vars, printed: session.printed, output: session.output, result }
+    }
+}
+
+struct Session<'a, I, O> {
+    machine: &'a mut Machine<I, O>,
+    vars: HashMap<String, String>,
+    printed: Vec<String>,
+    output: String,
+    /// Output after the last match.
+    unmatched: String,
+    timeout: Option<u64>,
+}
+
+fn fail<T>(line: usize, message: String) -> Result<T, ScriptFailure> {
+    Err(ScriptFailure { line, message })
+}
+
+impl<'a, I: Input + Extend<i128>, O: Output> Session<'a, I, O> {
+    fn execute(&mut self, statements: &[Statement]) -> Result<(), ScriptFailure> {
+        for statement in statements {
+            let line = statement.line;
+            match &statement.command {
+                Command::Expect(expected, capture) => {
+                    let found = self.expect(expected, line)?;
+                    if let Some(name) = capture {
+                        self.vars.insert(name.clone(), found);
+                    }
+                }
+                Command::Send(value) => {
+                    let value = self.value(value, line)?;
+                    let chars = value.chars().map(|c| c as i128).chain(iter::once('\n' as i128));
+                    self.machine.input.extend(chars);
+                }
+                Command::Set(name, value) => {
+                    let value = self.value(value, line)?;
+                    self.vars.insert(name.clone(), value);
+                }
+                Command::Print(value) => {
+                    let value = self.value(value, line)?;
+                    self.printed.push(value);
+                }
+                Command::Timeout(ms) => self.timeout = *ms,
+                Command::Repeat(count, body) => {
+                    let count = self.value(count, line)?;
+                    let count: u64 = match count.trim().parse() {
+                        Ok(count) => count,
+                        Err(_) => return fail(line, format!("repeat count '{}' is not a number", count)),
+                    };
+                    for _ in 0..count {
+                        self.execute(body)?;
+                    }
+                }
+                Command::For(name, list, pattern, body) => {
+                    let list = self.value(list, line)?;
+                    let items: Vec<String> = match pattern {
+                        Some(pattern) => pattern
+                            .find_all(&list)
+                            .iter()
+                            .map(|m| m.group(&list, 1).or_else(|| m.group(&list, 0)).unwrap())
+                            .collect(),
+                        None => list.lines().filter(|l| !l.trim().is_empty()).map(String::from).collect(),
+                    };
+                    for item in items {
+                        self.vars.insert(name.clone(), item);
+                        self.execute(body)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&self, text: &Text, line: usize) -> Result<String, ScriptFailure> {
+        let mut value = String::new();
+        for part in &text.0 {
+            match part {
+                Part::Literal(s) => value.push_str(s),
+                Part::Var(name) => match self.vars.get(name) {
+                    Some(s) => value.push_str(s),
+                    None => return fail(line, format!("variable '{}' is not set", name)),
+                },
+            }
+        }
+        Ok(value)
+    }
+
+    /// Where `expected` first matches the unmatched output: the byte offset just past the
+    /// match, and the text to capture.
+    fn find(&self, expected: &Expected, text: &Option<String>) -> Option<(usize, String)> {
+        match (expected, text) {
+            (Expected::Text(_), Some(text)) => self.unmatched.find(text.as_str()).map(|i| (i + text.len(), text.clone())),
+            (Expected::Pattern(pattern, _), _) => {
+                let m = pattern.find(&self.unmatched)?;
+                let end = self.unmatched.char_indices().nth(m.end()).map_or(self.unmatched.len(), |(i, _)| i);
+                let captured = m.group(&self.unmatched, 1).or_else(|| m.group(&self.unmatched, 0)).unwrap();
+                Some((end, captured))
+            }
+            _ => None,
+        }
+    }
+
+    /// Runs the program until its output matches, giving the captured text.
+    fn expect(&mut self, expected: &Expected, line: usize) -> Result<String, ScriptFailure> {
+        let text = match expected {
+            Expected::Text(text) => Some(self.value(text, line)?),
+            Expected::Pattern(..) => None,
+        };
+        let limits = self.machine.limits;
+        if let Some(ms) = self.timeout {
+            self.machine.limits.deadline = Limits::timeout(Duration::from_millis(ms));
+        }
+        let result = self.wait_for(expected, &text, line);
+        self.machine.limits.deadline = limits.deadline;
+        result
+    }
+
+    fn wait_for(&mut self, expected: &Expected, text: &Option<String>, line: usize) -> Result<String, ScriptFailure> {
+        // only look again once a line is complete, or the program stops
+        let mut look = true;
+        loop {
+            if look {
+                if let Some((end, captured)) = self.find(expected, text) {
+                    self.unmatched.drain(..end);
+                    return Ok(captured);
+                }
+            }
+            let event = match self.machine.run_until_event() {
+                Ok(event) => event,
+                Err(e) => return self.mismatch(expected, line, format!("the program failed: {}", e)),
+            };
+            let reason = match event {
+                Event::Output(x) => {
+                    let printed = if (0..128).contains(&x) { (x as u8 as char).to_string() } else { format!("{}\n", x) };
+                    look = printed.ends_with('\n');
+                    self.output.push_str(&printed);
+                    self.unmatched.push_str(&printed);
+                    continue;
+                }
+                Event::NeedsInput => "the program is waiting for input".to_string(),
+                Event::Halted => "the program halted".to_string(),
+                Event::Limit(Limit::Time) => match self.timeout {
+                    Some(ms) => format!("timed out after {} ms", ms),
+                    None => Limit::Time.to_string(),
+                },
+                Event::Limit(limit) => limit.to_string(),
+            };
+            if let Some((end, captured)) = self.find(expected, text) {
+                self.unmatched.drain(..end);
+                return Ok(captured);
+            }
+            return self.mismatch(expected, line, reason);
+        }
+    }
+
+    fn mismatch<T>(&self, expected: &Expected, line: usize, reason: String) -> Result<T, ScriptFailure> {
+        let message = if self.unmatched.is_empty() {
+            format!("expected {} but {}, with no new output", expected, reason)
+        } else {
+            format!("expected {} but {}, after printing:\n{}", expected, reason, self.unmatched.trim_end())
+        };
+        fail(line, message)
+    }
+}
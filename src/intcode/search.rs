@@ -0,0 +1,62 @@
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+use super::{IntcodeError, Machine, Output};
+
+/// A machine fed through its input queue, as searched here.
+type Queued<O> = Machine<VecDeque<i128>, O>;
+
+/// A state found by `bfs`, with the machine that got there.
+#[derive(Debug, Clone)]
+pub struct Reached<S, O = ()> {
+    pub state: S,
+    pub machine: Queued<O>,
+    /// Number of inputs fed since the start.
+    pub steps: usize,
+}
+
+/// Forks `m`, feeds it `input` and runs the fork to its next output.
+/// `m` itself is left untouched. The output is `None` if the fork halts or wants more input first.
+pub fn try_input<O: Output + Clone>(m: &Queued<O>, input: i128) -> Result<(Queued<O>, Option<i128>), IntcodeError> {
+    let mut fork = m.fork();
+    fork.push_input(input);
+    let output = fork.next_output()?;
+    Ok((fork, output))
+}
+
+/// Breadth-first search over the states of a machine that answers each input with one output,
+/// like the day15 repair droid. From every state reached, each of `inputs` is tried on a fork
+/// of its machine, and `next(state, input, output)` gives the state that leads to, or `None` if
+/// the move goes nowhere. Each state is expanded once.
+///
+/// Returns every state reached, the start included, in the order they were found,
+/// so `steps` never decreases and the last entry is the furthest away.
+pub fn bfs<S, O, F>(start: Queued<O>, state: S, inputs: &[i128], mut next: F)
+    -> Result<Vec<Reached<S, O>>, IntcodeError>
+where
+    S: Clone + Eq + Hash,
+    O: Output + Clone,
+    F: FnMut(&S, i128, i128) -> Option<S>,
+{
+    let mut seen = HashSet::new();
+    seen.insert(state.clone());
+    let mut reached = vec![Reached { state, machine: start, steps: 0 }];
+    let mut i = 0;
+    while i < reached.len() {
+        for &input in inputs {
+            let (machine, output) = try_input(&reached[i].machine, input)?;
+            let output = match output {
+                Some(output) => output,
+                None => continue,
+            };
+            if let Some(state) = next(&reached[i].state, input, output) {
+                if seen.insert(state.clone()) {
+                    let steps = reached[i].steps + 1;
+                    reached.push(Reached { state, machine, steps });
+                }
+            }
+        }
+        i += 1;
+    }
+    Ok(reached)
+}
@@ -0,0 +1,310 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::{Input, Limits, Machine, Memory, Output, new_machine_with_io};
+
+/// Bumped whenever the layout below changes. Older versions are still read.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+const MAGIC: &str = "intcode-snapshot";
+
+/// Everything needed to rebuild a machine: registers, modes, limits, RAM and the
+/// input it has been given but not read yet. The devices themselves are not saved,
+/// and neither is a deadline, which only means something to the process that set it.
+///
+/// On disk it is plain text, one field per line:
+///
+/// ```text
+/// intcode-snapshot 2
+/// id 0
+/// ip 4
+/// base 0
+/// checked 1
+/// strict 0
+/// steps 2
+/// limits 1000 - -
+/// input 2 10 -3
+/// image 6
+/// ram 3
+/// 0 3
+/// 1 5
+/// 5 42
+/// ```
+///
+/// `limits` holds the step, address and cell limits, `-` where there is none. `input` is
+/// followed by the number of queued values and the values, `image` by the length of the
+/// dense image, and `ram` by the number of `address value` lines after it. Only non-zero
+/// cells are listed.
+///
+/// Version 1 files stop after `base` and have no `image` line. They load with the
+/// default modes and limits, no steps taken and every cell paged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub id: i32,
+    pub ip: i128,
+    pub base: i128,
+    pub checked: bool,
+    pub strict: bool,
+    pub steps: u64,
+    /// The limits without their deadline.
+    pub limits: Limits,
+    pub ram: Memory,
+    pub input: VecDeque<i128>,
+}
+
+/// Why a snapshot could not be read or written.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The header names a version this build does not understand.
+    UnsupportedVersion(u32),
+    /// Line `line` (counting from 1) is not what the format expects.
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot io error: {}", e),
+            SnapshotError::UnsupportedVersion(v) =>
+                write!(f, "unsupported snapshot version {} (expected at most {})", v, SNAPSHOT_VERSION),
+            SnapshotError::Malformed { line, message } =>
+                write!(f, "malformed snapshot at line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> SnapshotError {
+        SnapshotError::Io(e)
+    }
+}
+
+impl Snapshot {
+    /// Captures `m`, with `input` as the values still waiting to be read.
+    /// Use `Machine::snapshot` when the machine keeps its input in a queue.
+    pub fn of<I, O>(m: &Machine<I, O>, input: VecDeque<i128>) -> Snapshot {
+        Snapshot {
+            id: m.id,
+            ip: m.ip,
+            base: m.base,
+            checked: m.checked,
+            strict: m.strict,
+            steps: m.steps,
+            limits: Limits { deadline: None, ..m.limits },
+            ram: m.ram.clone(),
+            input,
+        }
+    }
+
+    /// A machine that carries on from the snapshot, queueing the saved input.
+    pub fn restore(mut self) -> Machine {
+        let input = std::mem::take(&mut self.input);
+        self.restore_with_io(input, ())
+    }
+
+    /// Like `restore`, but with other devices attached. The saved input queue is dropped,
+    /// so read it out of the snapshot first if the new input device should replay it.
+    pub fn restore_with_io<I: Input, O: Output>(self, input: I, output: O) -> Machine<I, O> {
+        let mut m = new_machine_with_io(self.ram, input, output);
+        m.id = self.id;
+        m.ip = self.ip;
+        m.base = self.base;
+        m.checked = self.checked;
+        m.strict = self.strict;
+        m.steps = self.steps;
+        m.limits = self.limits;
+        m
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "{} {}", MAGIC, SNAPSHOT_VERSION)?;
+        writeln!(w, "id {}", self.id)?;
+        writeln!(w, "ip {}", self.ip)?;
+        writeln!(w, "base {}", self.base)?;
+        writeln!(w, "checked {}", self.checked as u8)?;
+        writeln!(w, "strict {}", self.strict as u8)?;
+        writeln!(w, "steps {}", self.steps)?;
+        writeln!(w, "limits {} {} {}", optional(self.limits.steps), optional(self.limits.max_address),
+            optional(self.limits.max_cells))?;
+        write!(w, "input {}", self.input.len())?;
+        for value in self.input.iter() {
+            write!(w, " {}", value)?;
+        }
+        writeln!(w)?;
+        writeln!(w, "image {}", self.ram.image_len())?;
+        let cells: Vec<(i128, i128)> = self.ram.iter().collect();
+        writeln!(w, "ram {}", cells.len())?;
+        for (address, value) in cells {
+            writeln!(w, "{} {}", address, value)?;
+        }
+        w.flush()
+    }
+
+    pub fn read_from<R: BufRead>(r: R) -> Result<Snapshot, SnapshotError> {
+        let mut lines = Lines { inner: r.lines(), number: 0 };
+
+        let header = lines.fields()?;
+        if header.len() != 2 || header[0] != MAGIC {
+            return Err(lines.malformed(format!("expected '{} <version>'", MAGIC)));
+        }
+        let version: u32 = lines.parse(&header[1])?;
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let id = lines.field("id")?;
+        let ip = lines.field("ip")?;
+        let base = lines.field("base")?;
+        let mut snapshot = Snapshot {
+            id,
+            ip,
+            base,
+            checked: false,
+            strict: false,
+            steps: 0,
+            limits: Limits::default(),
+            ram: Memory::new(),
+            input: VecDeque::new(),
+        };
+        if version >= 2 {
+            snapshot.checked = lines.flag("checked")?;
+            snapshot.strict = lines.flag("strict")?;
+            snapshot.steps = lines.field("steps")?;
+            let fields = lines.fields()?;
+            if fields.len() != 4 || fields[0] != "limits" {
+                return Err(lines.malformed("expected 'limits <steps> <address> <cells>'".to_string()));
+            }
+            snapshot.limits.steps = lines.optional(&fields[1])?;
+            snapshot.limits.max_address = lines.optional(&fields[2])?;
+            snapshot.limits.max_cells = lines.optional(&fields[3])?;
+        }
+
+        let fields = lines.fields()?;
+        if fields.len() < 2 || fields[0] != "input" {
+            return Err(lines.malformed("expected 'input <count> <values>'".to_string()));
+        }
+        let count: usize = lines.parse(&fields[1])?;
+        if fields.len() != count + 2 {
+            return Err(lines.malformed(format!("expected {} input values, found {}", count, fields.len() - 2)));
+        }
+        for value in &fields[2..] {
+            snapshot.input.push_back(lines.parse(value)?);
+        }
+
+        let image_len: usize = if version >= 2 { lines.field("image")? } else { 0 };
+        let cells: usize = lines.field("ram")?;
+        let mut image = vec![0; image_len];
+        let mut paged = Vec::new();
+        let mut last = -1;
+        for _ in 0..cells {
+            let fields = lines.fields()?;
+            if fields.len() != 2 {
+                return Err(lines.malformed("expected '<address> <value>'".to_string()));
+            }
+            let address: i128 = lines.parse(&fields[0])?;
+            if address <= last {
+                return Err(lines.malformed(format!("address {} is out of order", address)));
+            }
+            last = address;
+            let value = lines.parse(&fields[1])?;
+            if address < image_len as i128 {
+                image[address as usize] = value;
+            } else {
+                paged.push((address, value));
+            }
+        }
+        snapshot.ram = Memory::from(image);
+        for (address, value) in paged {
+            snapshot.ram.set(address, value);
+        }
+
+        if let Some(line) = lines.inner.next() {
+            if !line?.trim().is_empty() {
+                lines.number += 1;
+                return Err(lines.malformed("unexpected data after the last ram cell".to_string()));
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot, SnapshotError> {
+        let file = File::open(path)?;
+        Snapshot::read_from(BufReader::new(file))
+    }
+}
+
+impl<O> Machine<VecDeque<i128>, O> {
+    /// Captures the machine together with its queued input.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::of(self, self.input.clone())
+    }
+}
+
+/// A limit as written in the `limits` line.
+fn optional<T: fmt::Display>(limit: Option<T>) -> String {
+    match limit {
+        Some(limit) => limit.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Line reader that remembers where it is, for error messages.
+struct Lines<B> {
+    inner: io::Lines<B>,
+    number: usize,
+}
+
+impl<B: BufRead> Lines<B> {
+    fn malformed(&self, message: String) -> SnapshotError {
+        SnapshotError::Malformed { line: self.number, message }
+    }
+
+    fn fields(&mut self) -> Result<Vec<String>, SnapshotError> {
+        self.number += 1;
+        match self.inner.next() {
+            Some(line) => Ok(line?.split_whitespace().map(String::from).collect()),
+            None => Err(self.malformed("unexpected end of file".to_string())),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self, field: &str) -> Result<T, SnapshotError> {
+        field.parse().map_err(|_| self.malformed(format!("bad number '{}'", field)))
+    }
+
+    /// Reads a `name value` line.
+    fn field<T: std::str::FromStr>(&mut self, name: &str) -> Result<T, SnapshotError> {
+        let fields = self.fields()?;
+        if fields.len() != 2 || fields[0] != name {
+            return Err(self.malformed(format!("expected '{} <value>'", name)));
+        }
+        self.parse(&fields[1])
+    }
+
+    /// Reads a `name 0` or `name 1` line.
+    fn flag(&mut self, name: &str) -> Result<bool, SnapshotError> {
+        match self.field::<u8>(name)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            flag => Err(self.malformed(format!("expected 0 or 1 for {}, found {}", name, flag))),
+        }
+    }
+
+    /// Parses a limit, `-` meaning none.
+    fn optional<T: std::str::FromStr>(&self, field: &str) -> Result<Option<T>, SnapshotError> {
+        if field == "-" { Ok(None) } else { self.parse(field).map(Some) }
+    }
+}
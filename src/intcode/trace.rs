@@ -0,0 +1,315 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::disasm::{opcode_info, write_param};
+use super::word::saturate;
+use super::{Event, Input, IntcodeError, Machine, Output, Word, execute, get_index, get_instr, get_write_index,
+    parse_instr};
+
+/// Bumped whenever the line layout below changes.
+pub const TRACE_VERSION: u32 = 1;
+
+const MAGIC: &str = "intcode-trace";
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub ip: i128,
+    /// The instruction word, modes included.
+    pub instr: i128,
+    /// The value of each parameter that is read, and the address for the one that is written.
+    pub operands: Vec<i128>,
+    /// The address written and the value stored there.
+    pub write: Option<(i128, i128)>,
+    /// The new relative base, if the instruction changed it.
+    pub base: Option<i128>,
+    /// A value read from input, or written to output.
+    pub io: Option<Io>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Io {
+    In(i128),
+    Out(i128),
+}
+
+impl fmt::Display for Io {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Io::In(value) => write!(f, "in {}", value),
+            Io::Out(value) => write!(f, "out {}", value),
+        }
+    }
+}
+
+impl Step {
+    pub fn opcode(&self) -> i128 {
+        parse_instr(self.instr).0
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        opcode_info(self.opcode()).map(|(name, _)| name).unwrap_or("???")
+    }
+}
+
+/// Shown as `ip: mnemonic operands` followed by what changed. The written operand is shown as `[address]`.
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let written = write_param(self.opcode());
+        let operands: Vec<String> = self.operands.iter().enumerate().map(|(i, x)| {
+            if Some(i) == written { format!("[{}]", x) } else { x.to_string() }
+        }).collect();
+        let mut text = format!("{:>6}: {:<4} {:<30}", self.ip, self.mnemonic(), operands.join(", "));
+        if let Some((address, value)) = self.write {
+            text += &format!(" [{}] <- {}", address, value);
+        }
+        if let Some(base) = self.base {
+            text += &format!(" rb <- {}", base);
+        }
+        if let Some(io) = self.io {
+            text += &format!(" {}", io);
+        }
+        f.write_str(text.trim_end())
+    }
+}
+
+/// The instructions a machine has executed while tracing was on. Start one with `Machine::start_trace`.
+/// Words too big for an `i128` are recorded clamped.
+///
+/// On disk there is a version header and then one line per step:
+///
+/// ```text
+/// intcode-trace 1
+/// 0 3 11 w11=5 i5
+/// 2 1002 5,2,11 w11=10
+/// 6 4 10 o10
+/// 8 1105 1,0
+/// 0 109 -2 b-2
+/// 2 99 -
+/// ```
+///
+/// giving the ip, the instruction word and the operands, or `-` for none, then `w<address>=<value>`
+/// for a write, `b<base>` for a new relative base and `i<value>` or `o<value>` for I/O.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+}
+
+/// Which steps the viewer shows. Empty fields let everything through.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only instructions with an ip in `from..=to`.
+    pub range: Option<(i128, i128)>,
+    /// Only these mnemonics, like `jt` or `out`.
+    pub kinds: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, step: &Step) -> bool {
+        if let Some((from, to)) = self.range {
+            if step.ip < from || step.ip > to {
+                return false;
+            }
+        }
+        self.kinds.is_empty() || self.kinds.iter().any(|kind| kind == step.mnemonic())
+    }
+}
+
+/// Why a trace could not be read or written.
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    /// Line `line` (counting from 1) is not what the format expects.
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io(e) => write!(f, "trace io error: {}", e),
+            TraceError::UnsupportedVersion(v) =>
+                write!(f, "unsupported trace version {} (expected at most {})", v, TRACE_VERSION),
+            TraceError::Malformed { line, message } =>
+                write!(f, "malformed trace at line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(e: io::Error) -> TraceError {
+        TraceError::Io(e)
+    }
+}
+
+impl Trace {
+    pub fn filter<'a>(&'a self, filter: &'a Filter) -> impl Iterator<Item = &'a Step> + 'a {
+        self.steps.iter().filter(move |step| filter.matches(step))
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "{} {}", MAGIC, TRACE_VERSION)?;
+        for step in &self.steps {
+            write!(w, "{} {} ", step.ip, step.instr)?;
+            if step.operands.is_empty() {
+                write!(w, "-")?;
+            }
+            for (i, x) in step.operands.iter().enumerate() {
+                write!(w, "{}{}", if i == 0 { "" } else { "," }, x)?;
+            }
+            if let Some((address, value)) = step.write {
+                write!(w, " w{}={}", address, value)?;
+            }
+            if let Some(base) = step.base {
+                write!(w, " b{}", base)?;
+            }
+            match step.io {
+                Some(Io::In(value)) => write!(w, " i{}", value)?,
+                Some(Io::Out(value)) => write!(w, " o{}", value)?,
+                None => {}
+            }
+            writeln!(w)?;
+        }
+        w.flush()
+    }
+
+    pub fn read_from<R: BufRead>(r: R) -> Result<Trace, TraceError> {
+        let mut trace = Trace::default();
+        let mut header = false;
+        for (i, line) in r.lines().enumerate() {
+            let line = line?;
+            let malformed = |message: String| TraceError::Malformed { line: i + 1, message };
+            let number = |field: &str| field.parse::<i128>()
+                .map_err(|_| malformed(format!("bad number '{}'", field)));
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if i == 0 {
+                if fields.len() != 2 || fields[0] != MAGIC {
+                    return Err(malformed(format!("expected '{} <version>'", MAGIC)));
+                }
+                let version: u32 = fields[1].parse()
+                    .map_err(|_| malformed(format!("bad number '{}'", fields[1])))?;
+                if version == 0 || version > TRACE_VERSION {
+                    return Err(TraceError::UnsupportedVersion(version));
+                }
+                header = true;
+                continue;
+            }
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() < 3 {
+                return Err(malformed("expected '<ip> <instr> <operands>'".to_string()));
+            }
+            let mut step = Step {
+                ip: number(fields[0])?,
+                instr: number(fields[1])?,
+                operands: Vec::new(),
+                write: None,
+                base: None,
+                io: None,
+            };
+            if fields[2] != "-" {
+                for x in fields[2].split(',') {
+                    step.operands.push(number(x)?);
+                }
+            }
+            for field in &fields[3..] {
+                let tag = field.chars().next().unwrap();
+                let rest = &field[tag.len_utf8()..];
+                match tag {
+                    'w' => {
+                        let mut parts = rest.splitn(2, '=');
+                        let address = number(parts.next().unwrap_or(""))?;
+                        let value = number(parts.next().unwrap_or(""))?;
+                        step.write = Some((address, value));
+                    }
+                    'b' => step.base = Some(number(rest)?),
+                    'i' => step.io = Some(Io::In(number(rest)?)),
+                    'o' => step.io = Some(Io::Out(number(rest)?)),
+                    _ => return Err(malformed(format!("unknown field '{}'", field))),
+                }
+            }
+            trace.steps.push(step);
+        }
+        if !header {
+            return Err(TraceError::Malformed { line: 1, message: "missing header".to_string() });
+        }
+        Ok(trace)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TraceError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Trace, TraceError> {
+        let file = File::open(path)?;
+        Trace::read_from(BufReader::new(file))
+    }
+}
+
+impl<I, O, W> Machine<I, O, W> {
+    /// Records every instruction executed from now on, until `take_trace`.
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::default());
+    }
+
+    /// Stops tracing and hands over what was recorded.
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+}
+
+/// `execute`, noting down what the instruction did. Operands are resolved with the
+/// same functions the instruction uses, before it runs. A step that faults or waits
+/// for input changes nothing, so it is not recorded.
+pub(super) fn traced_step<I: Input<W>, O: Output<W>, W: Word>(m: &mut Machine<I, O, W>) -> Result<Option<Event<W>>, IntcodeError> {
+    let ip = m.ip;
+    let base = m.base;
+    let instr = get_instr(m)?;
+    let (opcode, m1, m2, m3) = parse_instr(instr);
+    let count = opcode_info(opcode).map(|(_, count)| count).unwrap_or(0);
+    let written = write_param(opcode);
+    let mut operands = Vec::new();
+    for (i, &mode) in [m1, m2, m3][..count].iter().enumerate() {
+        let operand = if Some(i) == written {
+            get_write_index(m, i as i128 + 1, mode)
+        } else {
+            get_index(m, i as i128 + 1, mode).map(|x| saturate(&x))
+        };
+        match operand {
+            Ok(x) => operands.push(x),
+            // let the instruction report it
+            Err(_) => return execute(m),
+        }
+    }
+
+    let event = execute(m)?;
+    if event == Some(Event::NeedsInput) {
+        return Ok(event);
+    }
+    let mut step = Step { ip, instr, operands, write: None, base: None, io: None };
+    if let Some(i) = written {
+        let address = step.operands[i];
+        step.write = Some((address, saturate(&m.ram.get(address))));
+    }
+    if m.base != base {
+        step.base = Some(m.base);
+    }
+    if opcode == 3 {
+        step.io = step.write.map(|(_, value)| Io::In(value));
+    }
+    if let Some(Event::Output(ref value)) = event {
+        step.io = Some(Io::Out(saturate(value)));
+    }
+    if let Some(trace) = m.trace.as_mut() {
+        trace.steps.push(step);
+    }
+    Ok(event)
+}
@@ -0,0 +1,371 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::trace::Io;
+use super::{Input, IntcodeError, Memory, Output, new_machine_with_io};
+
+/// Bumped whenever the line layout below changes.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+const MAGIC: &str = "intcode-transcript";
+
+/// One value that went into or came out of a machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// Milliseconds since recording started.
+    pub time: u64,
+    /// The id of the machine it belongs to.
+    pub machine: i32,
+    pub io: Io,
+}
+
+/// Every value machines read and wrote during a session, in the order it happened.
+/// Record one with a `Recorder` and check a program against it with `replay`.
+///
+/// On disk there is a version header and then one line per value:
+///
+/// ```text
+/// intcode-transcript 1
+/// 0 0 o68
+/// 2 0 o111
+/// 3519 0 i110
+/// ```
+///
+/// giving the time in milliseconds, the machine id and `i<value>` or `o<value>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transcript {
+    pub entries: Vec<Entry>,
+}
+
+/// Why a transcript could not be read or written.
+#[derive(Debug)]
+pub enum TranscriptError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    /// Line `line` (counting from 1) is not what the format expects.
+    Malformed { line: usize, message: String },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TranscriptError::Io(e) => write!(f, "transcript io error: {}", e),
+            TranscriptError::UnsupportedVersion(v) =>
+                write!(f, "unsupported transcript version {} (expected at most {})", v, TRANSCRIPT_VERSION),
+            TranscriptError::Malformed { line, message } =>
+                write!(f, "malformed transcript at line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for TranscriptError {}
+
+impl From<io::Error> for TranscriptError {
+    fn from(e: io::Error) -> TranscriptError {
+        TranscriptError::Io(e)
+    }
+}
+
+impl Transcript {
+    /// The ids of every machine in the transcript.
+    pub fn machines(&self) -> BTreeSet<i32> {
+        self.entries.iter().map(|entry| entry.machine).collect()
+    }
+
+    pub fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "{} {}", MAGIC, TRANSCRIPT_VERSION)?;
+        for entry in &self.entries {
+            match entry.io {
+                Io::In(value) => writeln!(w, "{} {} i{}", entry.time, entry.machine, value)?,
+                Io::Out(value) => writeln!(w, "{} {} o{}", entry.time, entry.machine, value)?,
+            }
+        }
+        w.flush()
+    }
+
+    pub fn read_from<R: BufRead>(r: R) -> Result<Transcript, TranscriptError> {
+        let mut transcript = Transcript::default();
+        let mut header = false;
+        for (i, line) in r.lines().enumerate() {
+            let line = line?;
+            let malformed = |message: String| TranscriptError::Malformed { line: i + 1, message };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if i == 0 {
+                if fields.len() != 2 || fields[0] != MAGIC {
+                    return Err(malformed(format!("expected '{} <version>'", MAGIC)));
+                }
+                let version: u32 = fields[1].parse()
+                    .map_err(|_| malformed(format!("bad number '{}'", fields[1])))?;
+                if version == 0 || version > TRANSCRIPT_VERSION {
+                    return Err(TranscriptError::UnsupportedVersion(version));
+                }
+                header = true;
+                continue;
+            }
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != 3 {
+                return Err(malformed("expected '<time> <machine> i<value>|o<value>'".to_string()));
+            }
+            let time = fields[0].parse().map_err(|_| malformed(format!("bad time '{}'", fields[0])))?;
+            let machine = fields[1].parse().map_err(|_| malformed(format!("bad machine id '{}'", fields[1])))?;
+            let tag = fields[2].chars().next().unwrap();
+            let value = fields[2][tag.len_utf8()..].parse()
+                .map_err(|_| malformed(format!("bad value '{}'", fields[2])))?;
+            let io = match tag {
+                'i' => Io::In(value),
+                'o' => Io::Out(value),
+                _ => return Err(malformed(format!("expected i or o, found '{}'", fields[2]))),
+            };
+            transcript.entries.push(Entry { time, machine, io });
+        }
+        if !header {
+            return Err(TranscriptError::Malformed { line: 1, message: "missing header".to_string() });
+        }
+        Ok(transcript)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TranscriptError> {
+        let file = File::create(path)?;
+        self.write_to(BufWriter::new(file))?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Transcript, TranscriptError> {
+        let file = File::open(path)?;
+        Transcript::read_from(BufReader::new(file))
+    }
+}
+
+#[derive(Debug)]
+struct Recording {
+    start: Instant,
+    transcript: Transcript,
+}
+
+/// Collects a transcript from devices wrapped with `record`. Clones share the same
+/// transcript, so it can be handed to several machines or threads.
+#[derive(Debug, Clone)]
+pub struct Recorder(Arc<Mutex<Recording>>);
+
+impl Default for Recorder {
+    fn default() -> Recorder {
+        Recorder::new()
+    }
+}
+
+impl Recorder {
+    /// Starts the clock for the timestamps.
+    pub fn new() -> Recorder {
+        Recorder(Arc::new(Mutex::new(Recording { start: Instant::now(), transcript: Transcript::default() })))
+    }
+
+    /// Wraps an input or output device so every value through it is recorded as machine `machine`'s.
+    pub fn record<D>(&self, machine: i32, device: D) -> Recorded<D> {
+        Recorded { inner: device, machine, recorder: self.clone() }
+    }
+
+    /// What has been recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.0.lock().unwrap().transcript.clone()
+    }
+
+    fn push(&self, machine: i32, io: Io) {
+        let mut recording = self.0.lock().unwrap();
+        let time = recording.start.elapsed().as_millis() as u64;
+        recording.transcript.entries.push(Entry { time, machine, io });
+    }
+}
+
+/// A device that records what passes through it. Make one with `Recorder::record`.
+#[derive(Debug, Clone)]
+pub struct Recorded<D> {
+    pub inner: D,
+    pub machine: i32,
+    recorder: Recorder,
+}
+
+/// Only values actually read are recorded, not attempts that found nothing.
+impl<D: Input> Input for Recorded<D> {
+    fn read_input(&mut self) -> Option<i128> {
+        let value = self.inner.read_input()?;
+        self.recorder.push(self.machine, Io::In(value));
+        Some(value)
+    }
+}
+
+/// Queues input on the wrapped device. It is recorded once the machine reads it.
+impl<D: Extend<i128>> Extend<i128> for Recorded<D> {
+    fn extend<T: IntoIterator<Item = i128>>(&mut self, values: T) {
+        self.inner.extend(values);
+    }
+}
+
+impl<D: Output> Output for Recorded<D> {
+    fn write_output(&mut self, value: i128) {
+        self.recorder.push(self.machine, Io::Out(value));
+        self.inner.write_output(value);
+    }
+}
+
+/// Where a replayed machine first did something other than what was recorded.
+/// `index` counts entries of the whole transcript from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The machine output `got` where the transcript has `expected`, or nothing if the
+    /// machine's part of the transcript was used up.
+    UnexpectedOutput { machine: i32, index: usize, expected: Option<Io>, got: i128 },
+    /// The machine asked for input where the transcript has an output.
+    UnexpectedInput { machine: i32, index: usize, expected: Io },
+    /// The machine stopped with `remaining` of its entries left, the first of them at `index`.
+    Unfinished { machine: i32, index: usize, remaining: usize },
+    Fault { machine: i32, error: IntcodeError },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::UnexpectedOutput { machine, index, expected: Some(expected), got } =>
+                write!(f, "machine {} entry {}: expected {}, got out {}", machine, index, expected, got),
+            ReplayError::UnexpectedOutput { machine, index, expected: None, got } =>
+                write!(f, "machine {} entry {}: got out {} after the end of the transcript", machine, index, got),
+            ReplayError::UnexpectedInput { machine, index, expected } =>
+                write!(f, "machine {} entry {}: expected {}, but the machine asked for input", machine, index, expected),
+            ReplayError::Unfinished { machine, index, remaining } =>
+                write!(f, "machine {} stopped at entry {} with {} entries left", machine, index, remaining),
+            ReplayError::Fault { machine, error } => write!(f, "machine {}: {}", machine, error),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+#[derive(Debug)]
+struct Replaying {
+    /// Each machine's entries still to come, with their index in the transcript.
+    pending: HashMap<i32, VecDeque<(usize, Io)>>,
+    length: usize,
+    error: Option<ReplayError>,
+}
+
+impl Replaying {
+    fn fail(&mut self, error: ReplayError) {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+}
+
+/// Plays a transcript back to machines through devices made with `device`. Clones share
+/// the same position in the transcript.
+#[derive(Debug, Clone)]
+pub struct Replay(Arc<Mutex<Replaying>>);
+
+impl Replay {
+    pub fn new(transcript: &Transcript) -> Replay {
+        let mut pending: HashMap<i32, VecDeque<(usize, Io)>> = HashMap::new();
+        for (index, entry) in transcript.entries.iter().enumerate() {
+            pending.entry(entry.machine).or_default().push_back((index, entry.io));
+        }
+        let replaying = Replaying { pending, length: transcript.entries.len(), error: None };
+        Replay(Arc::new(Mutex::new(replaying)))
+    }
+
+    /// An input and output device for machine `machine`. Input gives the recorded values in
+    /// order and output is checked against what was recorded. After the first difference
+    /// input gives nothing, so the machine stops.
+    pub fn device(&self, machine: i32) -> Replayed {
+        Replayed { machine, replay: self.clone() }
+    }
+
+    /// The first difference from the transcript, or whatever is left of it unplayed.
+    pub fn finish(&self) -> Result<(), ReplayError> {
+        let replaying = self.0.lock().unwrap();
+        if let Some(error) = replaying.error {
+            return Err(error);
+        }
+        let mut unfinished: Vec<(i32, &VecDeque<(usize, Io)>)> = replaying.pending.iter()
+            .filter(|(_, pending)| !pending.is_empty())
+            .map(|(&machine, pending)| (machine, pending))
+            .collect();
+        unfinished.sort_by_key(|(_, pending)| pending[0].0);
+        match unfinished.first() {
+            Some(&(machine, pending)) =>
+                Err(ReplayError::Unfinished { machine, index: pending[0].0, remaining: pending.len() }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// One machine's end of a `Replay`.
+#[derive(Debug, Clone)]
+pub struct Replayed {
+    pub machine: i32,
+    replay: Replay,
+}
+
+impl Input for Replayed {
+    fn read_input(&mut self) -> Option<i128> {
+        let mut replaying = self.replay.0.lock().unwrap();
+        if replaying.error.is_some() {
+            return None;
+        }
+        let pending = replaying.pending.entry(self.machine).or_default();
+        match pending.front().cloned() {
+            Some((_, Io::In(value))) => {
+                pending.pop_front();
+                Some(value)
+            }
+            Some((index, expected)) => {
+                replaying.fail(ReplayError::UnexpectedInput { machine: self.machine, index, expected });
+                None
+            }
+            // the session ended with the machine waiting for input
+            None => None,
+        }
+    }
+}
+
+impl Output for Replayed {
+    fn write_output(&mut self, value: i128) {
+        let mut replaying = self.replay.0.lock().unwrap();
+        if replaying.error.is_some() {
+            return;
+        }
+        let length = replaying.length;
+        let pending = replaying.pending.entry(self.machine).or_default();
+        match pending.pop_front() {
+            Some((_, Io::Out(expected))) if expected == value => {}
+            Some((index, expected)) => replaying.fail(ReplayError::UnexpectedOutput {
+                machine: self.machine, index, expected: Some(expected), got: value,
+            }),
+            None => replaying.fail(ReplayError::UnexpectedOutput {
+                machine: self.machine, index: length, expected: None, got: value,
+            }),
+        }
+    }
+}
+
+/// Runs `program` on the inputs in `transcript`, one machine per id in it, and checks it
+/// outputs exactly what was recorded. Machines are replayed one after another, since
+/// each one's inputs are in the transcript.
+pub fn replay(program: &Memory, transcript: &Transcript) -> Result<(), ReplayError> {
+    let replay = Replay::new(transcript);
+    for id in transcript.machines() {
+        let mut m = new_machine_with_io(program.clone(), replay.device(id), replay.device(id));
+        m.id = id;
+        if let Err(error) = m.run() {
+            // a fault after the machine went off script is down to that
+            let diverged = replay.0.lock().unwrap().error;
+            return Err(diverged.unwrap_or(ReplayError::Fault { machine: id, error }));
+        }
+    }
+    replay.finish()
+}
@@ -0,0 +1,341 @@
+use std::error::Error;
+use std::fmt::{self, Write};
+
+use super::cfg::Cfg;
+use super::disasm::{Instruction, Operand, write_param};
+use super::{Event, IntcodeError, Machine, Memory, new_machine};
+
+/// Image words on one line of the generated `IMAGE`.
+const IMAGE_WIDTH: usize = 12;
+
+/// Straight line runs of instructions the generated code runs in one go. A segment ends
+/// where its block does, after an input or output since the machine hands control back
+/// there, and after a write to a fixed address further on in the block.
+fn segments(cfg: &Cfg) -> Vec<Vec<&Instruction>> {
+    let mut segments = Vec::new();
+    for block in cfg.blocks.values() {
+        let mut segment = Vec::new();
+        for instr in &block.instructions {
+            segment.push(instr);
+            let next = instr.address + instr.size() as i128;
+            let patches_block = match write_param(instr.opcode).map(|i| instr.operands[i]) {
+                Some(Operand::Position(address)) | Some(Operand::Immediate(address)) =>
+                    address >= next && address < block.end(),
+                _ => false,
+            };
+            if matches!(instr.opcode, 3 | 4) || patches_block {
+                segments.push(std::mem::take(&mut segment));
+            }
+        }
+        if !segment.is_empty() {
+            segments.push(segment);
+        }
+    }
+    segments
+}
+
+/// Hands the instruction at `ip` to the interpreter, which will fault on it. It has to run
+/// it there and then: going round the loop would only come back to the same arm.
+fn bail(ip: i128) -> String {
+    format!("m.ip = {}; if let Some(event) = run_one_step(m)? {{ return Ok(event); }} continue;", ip)
+}
+
+/// Lines that put the address `offset` from the base into `name`, leaving the instruction
+/// to the interpreter if that is negative or does not fit, like `bail`.
+fn relative(lines: &mut Vec<String>, ip: i128, offset: i128, name: &str) {
+    if offset == 0 {
+        lines.push(format!("let {} = m.base;", name));
+        lines.push(format!("if {} < 0 {{ {} }}", name, bail(ip)));
+    } else {
+        lines.push(format!("let {} = match m.base.checked_add({}) {{", name, offset));
+        lines.push("    Some(address) if address >= 0 => address,".to_string());
+        lines.push(format!("    _ => {{ {} }}", bail(ip)));
+        lines.push("};".to_string());
+    }
+}
+
+/// Lines that load `operand` into `name`, or `None` if it is a negative address.
+fn load(lines: &mut Vec<String>, ip: i128, operand: Operand, name: &str) -> Option<()> {
+    match operand {
+        Operand::Immediate(value) => lines.push(format!("let {}: i128 = {};", name, value)),
+        Operand::Position(address) if address >= 0 => lines.push(format!("let {} = m.ram.get({});", name, address)),
+        Operand::Position(_) => return None,
+        Operand::Relative(offset) => {
+            relative(lines, ip, offset, &format!("at_{}", name));
+            lines.push(format!("let {} = m.ram.get(at_{});", name, name));
+        }
+    }
+    Some(())
+}
+
+/// Lines that work out the address `operand` writes to, giving the expression for it,
+/// or `None` if it is a fixed negative address.
+fn target(lines: &mut Vec<String>, ip: i128, operand: Operand) -> Option<String> {
+    match operand {
+        Operand::Position(address) | Operand::Immediate(address) if address >= 0 => Some(address.to_string()),
+        Operand::Position(_) | Operand::Immediate(_) => None,
+        Operand::Relative(offset) => {
+            relative(lines, ip, offset, "to");
+            Some("to".to_string())
+        }
+    }
+}
+
+/// Rust for one instruction, `end` being where its segment ends. Gives `None` if the
+/// instruction can only fault, so the interpreter should run it.
+fn instruction(instr: &Instruction, end: i128) -> Option<Vec<String>> {
+    let ip = instr.address;
+    let next = ip + instr.size() as i128;
+    let operands = &instr.operands;
+    let mut lines = Vec::new();
+    match instr.opcode {
+        1 | 2 | 7 | 8 => {
+            load(&mut lines, ip, operands[0], "a")?;
+            load(&mut lines, ip, operands[1], "b")?;
+            let to = target(&mut lines, ip, operands[2])?;
+            let value = match instr.opcode {
+                1 => "a.wrapping_add(b)",
+                2 => "a.wrapping_mul(b)",
+                7 => "i128::from(a < b)",
+                _ => "i128::from(a == b)",
+            };
+            lines.push(format!("m.ram.set({}, {});", to, value));
+            lines.push("m.steps += 1;".to_string());
+            // the write might land in an instruction still to come in this segment
+            if to == "to" && next < end {
+                lines.push(format!("if ({}..{}).contains(&to) {{ m.ip = {}; continue; }}", next, end, next));
+            }
+        }
+        3 => {
+            let to = target(&mut lines, ip, operands[0])?;
+            lines.push("match m.input.read_input() {".to_string());
+            lines.push(format!("    Some(value) => m.ram.set({}, value),", to));
+            lines.push(format!("    None => {{ m.ip = {}; return Ok(Event::NeedsInput); }}", ip));
+            lines.push("}".to_string());
+            lines.push("m.steps += 1;".to_string());
+        }
+        4 => {
+            load(&mut lines, ip, operands[0], "a")?;
+            lines.push("m.output.write_output(a);".to_string());
+            lines.push("m.steps += 1;".to_string());
+            lines.push(format!("m.ip = {};", next));
+            lines.push("return Ok(Event::Output(a));".to_string());
+        }
+        5 | 6 => {
+            let taken = match operands[0] {
+                Operand::Immediate(x) => Some((x != 0) == (instr.opcode == 5)),
+                operand => {
+                    load(&mut lines, ip, operand, "a")?;
+                    None
+                }
+            };
+            let to = match operands[1] {
+                Operand::Immediate(to) => to.to_string(),
+                // still read when never taken, since the interpreter faults on a bad address
+                operand if taken == Some(false) => {
+                    load(&mut lines, ip, operand, "_b")?;
+                    String::new()
+                }
+                operand => {
+                    load(&mut lines, ip, operand, "b")?;
+                    "b".to_string()
+                }
+            };
+            lines.push("m.steps += 1;".to_string());
+            lines.push(match taken {
+                Some(true) => format!("m.ip = {};", to),
+                Some(false) => format!("m.ip = {};", next),
+                None => format!("m.ip = if a {} 0 {{ {} }} else {{ {} }};", if instr.opcode == 5 { "!=" } else { "==" }, to, next),
+            });
+        }
+        9 => {
+            load(&mut lines, ip, operands[0], "a")?;
+            lines.push("m.base = match m.base.checked_add(a) {".to_string());
+            lines.push("    Some(base) => base,".to_string());
+            lines.push(format!("    None => {{ {} }}", bail(ip)));
+            lines.push("};".to_string());
+            lines.push("m.steps += 1;".to_string());
+        }
+        _ => {
+            lines.push(format!("m.ip = {};", ip));
+            lines.push("m.steps += 1;".to_string());
+            lines.push("return Ok(Event::Halted);".to_string());
+        }
+    }
+    Some(lines)
+}
+
+/// Translates a program into the source of a Rust module that runs it without decoding
+/// instructions. `intcode` is the path the module reaches this one by, such as
+/// `aoc2019::intcode`.
+///
+/// The module has the image as `IMAGE`, `new_machine` to load it, and `run_until_event`,
+/// `run` and `next_output` that work like the `Machine` methods of the same names. Code
+/// `Cfg::build` finds becomes one `match` arm per straight line segment, which only runs
+/// while the segment's cells still hold the image's words. Anything else, like a jump
+/// through memory to code that was not found or an instruction the program rewrote, is
+/// left to the interpreter one step at a time, and so is every step of a machine that is
+/// checked, strict, limited, traced, profiled or watched. `check` runs the module and the
+/// interpreter side by side with `differential`.
+///
+/// ```ignore
+/// fs::write("src/day19_beam.rs", transpile(&program, "aoc2019::intcode"))?;
+/// ```
+pub fn transpile(program: &Memory, intcode: &str) -> String {
+    let cfg = Cfg::build(program);
+    let image: Vec<i128> = (0..program.image_len() as i128).map(|address| program.get(address)).collect();
+    let mut out = String::new();
+    writeln!(out, "//! Compiled from a {} word Intcode image by `intcode::transpile`. Regenerate it rather than", image.len()).unwrap();
+    out += "//! editing it.\n\n";
+    writeln!(out, "use {}::{{Divergence, Event, Input, IntcodeError, Limits, Machine, Memory, Output, differential, run_one_step}};\n", intcode).unwrap();
+
+    writeln!(out, "pub const IMAGE: [i128; {}] = [", image.len()).unwrap();
+    for words in image.chunks(IMAGE_WIDTH) {
+        let words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        writeln!(out, "    {},", words.join(", ")).unwrap();
+    }
+    out += "];\n\n";
+
+    writeln!(out, "pub fn new_machine() -> Machine {{\n    {}::new_machine(Memory::from(IMAGE.to_vec()))\n}}\n", intcode).unwrap();
+    out += "\
+/// Whether nothing checks or watches the machine's steps, so compiled code can take them.
+fn is_plain<I, O>(m: &Machine<I, O>) -> bool {
+    !m.checked && !m.strict && m.limits == Limits::default()
+        && m.trace.is_none() && m.profile.is_none() && m.code_watch.is_none()
+}
+
+/// Whether the cells from `start` up to `end` still hold the image's words.
+fn intact<I, O>(m: &Machine<I, O>, start: usize, end: usize) -> bool {
+    (start..end).all(|address| m.ram.get(address as i128) == IMAGE[address])
+}
+
+pub fn run_until_event<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    if !is_plain(m) {
+        return m.run_until_event();
+    }
+    loop {
+        match m.ip {
+";
+    for segment in segments(&cfg) {
+        let start = segment[0].address;
+        let last = segment[segment.len() - 1];
+        let end = last.address + last.size() as i128;
+        writeln!(out, "            {} if intact(m, {}, {}) => {{", start, start, end).unwrap();
+        let mut finished = false;
+        for instr in &segment {
+            writeln!(out, "                // {}: {}", instr.address, instr).unwrap();
+            let lines = instruction(instr, end).unwrap_or_else(|| vec![bail(instr.address)]);
+            finished = matches!(lines.last(), Some(line) if line.starts_with("return") || line.ends_with("continue;"))
+                || matches!(instr.opcode, 5 | 6);
+            for line in lines {
+                writeln!(out, "                {}", line).unwrap();
+            }
+            if finished {
+                break;
+            }
+        }
+        if !finished {
+            writeln!(out, "                m.ip = {};", end).unwrap();
+        }
+        out += "            }\n";
+    }
+    out += "            _ => {
+                if let Some(event) = run_one_step(m)? {
+                    return Ok(event);
+                }
+            }
+        }
+    }
+}
+
+pub fn run<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Event, IntcodeError> {
+    loop {
+        match run_until_event(m)? {
+            Event::Output(_) => {}
+            event => return Ok(event),
+        }
+    }
+}
+
+pub fn next_output<I: Input, O: Output>(m: &mut Machine<I, O>) -> Result<Option<i128>, IntcodeError> {
+    match run_until_event(m)? {
+        Event::Output(value) => Ok(Some(value)),
+        _ => Ok(None),
+    }
+}
+
+/// Runs this module and the interpreter side by side on `input`.
+pub fn check(input: &[i128]) -> Result<Vec<Event>, Divergence> {
+    differential(&Memory::from(IMAGE.to_vec()), run_until_event, input)
+}
+";
+    out
+}
+
+/// The first place compiled code and the interpreter went different ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The events both gave before that.
+    pub agreed: Vec<Event>,
+    pub interpreted: String,
+    pub compiled: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "after {} events the interpreter gave {} but the compiled code gave {}",
+            self.agreed.len(), self.interpreted, self.compiled)
+    }
+}
+
+impl Error for Divergence {}
+
+/// An event or fault and the state it left the machine in.
+fn outcome(m: &Machine, result: &Result<Event, IntcodeError>) -> String {
+    let result = match result {
+        Ok(event) => format!("{:?}", event),
+        Err(e) => e.to_string(),
+    };
+    format!("{} at ip {} with base {} after {} steps", result, m.ip, m.base, m.steps)
+}
+
+/// Runs `program` on the interpreter and with `run`, a transpiled module's
+/// `run_until_event`, on the same `input`. After every event the two have to agree on
+/// it and on the machine's ip, base, steps and memory. Gives the events once both halt,
+/// wait for input or fault the same way.
+pub fn differential(program: &Memory, run: fn(&mut Machine) -> Result<Event, IntcodeError>, input: &[i128]) -> Result<Vec<Event>, Divergence> {
+    let mut interpreted = new_machine(program.clone());
+    let mut compiled = new_machine(program.clone());
+    for &value in input {
+        interpreted.push_input(value);
+        compiled.push_input(value);
+    }
+    let mut agreed = Vec::new();
+    loop {
+        let expected = interpreted.run_until_event();
+        let actual = run(&mut compiled);
+        let (left, right) = (outcome(&interpreted, &expected), outcome(&compiled, &actual));
+        if left != right {
+            return Err(Divergence { agreed, interpreted: left, compiled: right });
+        }
+        let cell = interpreted.ram.iter()
+            .chain(compiled.ram.iter())
+            .map(|(address, _)| address)
+            .find(|&address| interpreted.ram.get(address) != compiled.ram.get(address));
+        if let Some(address) = cell {
+            return Err(Divergence {
+                agreed,
+                interpreted: format!("{} with [{}] = {}", left, address, interpreted.ram.get(address)),
+                compiled: format!("{} with [{}] = {}", right, address, compiled.ram.get(address)),
+            });
+        }
+        match expected {
+            Ok(Event::Output(value)) => agreed.push(Event::Output(value)),
+            Ok(event) => {
+                agreed.push(event);
+                return Ok(agreed);
+            }
+            Err(_) => return Ok(agreed),
+        }
+    }
+}
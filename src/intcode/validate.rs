@@ -0,0 +1,44 @@
+use super::disasm::{opcode_info, reachable_code, write_param};
+use super::{IntcodeError, Memory};
+
+/// Checks the instruction word `raw` at `ip` the way a strict machine does before running
+/// it: a known opcode, only modes 0, 1 and 2, no immediate mode on the parameter it writes
+/// to and no mode digits past its last parameter.
+pub fn check_instr(ip: i128, raw: i128) -> Result<(), IntcodeError> {
+    let opcode = raw % 100;
+    let count = match opcode_info(opcode) {
+        Some((_, count)) if raw > 0 => count,
+        _ => return Err(IntcodeError::BadOpcode { ip, opcode: raw }),
+    };
+    let mut modes = raw / 100;
+    for i in 0..count {
+        let mode = modes % 10;
+        if mode > 2 {
+            return Err(IntcodeError::BadMode { ip, opcode: raw, mode });
+        }
+        if mode == 1 && write_param(opcode) == Some(i) {
+            return Err(IntcodeError::ImmediateWrite { ip, opcode: raw });
+        }
+        modes /= 10;
+    }
+    if modes != 0 {
+        return Err(IntcodeError::ExtraDigits { ip, opcode: raw });
+    }
+    Ok(())
+}
+
+/// Every instruction reachable from address 0 that a strict machine would refuse to run,
+/// in address order. Reachability is worked out as for `disasm::reachable`, with the walk
+/// stopping at each word that is not an instruction.
+///
+/// A program can still fault at run time: on addresses it computes, on jumps through memory
+/// to code the walk did not find, and on code it writes for itself.
+pub fn preflight(program: &Memory) -> Vec<IntcodeError> {
+    let (code, dead_ends) = reachable_code(program, &[0]);
+    let mut errors: Vec<IntcodeError> = code.iter()
+        .chain(dead_ends.iter())
+        .filter_map(|&address| check_instr(address, program.get(address)).err())
+        .collect();
+    errors.sort_by_key(|e| e.ip());
+    errors
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use super::{Event, IntcodeError, new_machine_with_io, parse_words};
+
+/// A value an Intcode machine computes with: `i32`, `i64`, `i128` or `BigInt`.
+///
+/// The ip, the relative base and addresses are `i128` whatever the word type, so a
+/// word used as one of those has to fit in an `i128` or the machine faults.
+pub trait Word: Clone + Eq + Ord + fmt::Debug + fmt::Display + FromStr + From<i32> + Send + Sync + 'static {
+    /// What the word type is called in reports.
+    const NAME: &'static str;
+
+    fn to_i128(&self) -> Option<i128>;
+
+    /// `self + other`, or `None` if the sum does not fit in a word.
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+    /// What a machine that is not checked computes: the result wrapped around in two's complement.
+    fn wrapping_add(&self, other: &Self) -> Self;
+    fn wrapping_mul(&self, other: &Self) -> Self;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::from(0)
+    }
+}
+
+macro_rules! primitive_word {
+    ($t:ty) => {
+        impl Word for $t {
+            const NAME: &'static str = stringify!($t);
+
+            fn to_i128(&self) -> Option<i128> {
+                Some(*self as i128)
+            }
+
+            fn checked_add(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_add(*self, *other)
+            }
+
+            fn checked_mul(&self, other: &$t) -> Option<$t> {
+                <$t>::checked_mul(*self, *other)
+            }
+
+            fn wrapping_add(&self, other: &$t) -> $t {
+                <$t>::wrapping_add(*self, *other)
+            }
+
+            fn wrapping_mul(&self, other: &$t) -> $t {
+                <$t>::wrapping_mul(*self, *other)
+            }
+        }
+    };
+}
+
+primitive_word!(i32);
+primitive_word!(i64);
+primitive_word!(i128);
+
+/// Never overflows, so checked and unchecked machines compute the same.
+impl Word for BigInt {
+    const NAME: &'static str = "bignum";
+
+    fn to_i128(&self) -> Option<i128> {
+        ToPrimitive::to_i128(self)
+    }
+
+    fn checked_add(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &BigInt) -> Option<BigInt> {
+        Some(self * other)
+    }
+
+    fn wrapping_add(&self, other: &BigInt) -> BigInt {
+        self + other
+    }
+
+    fn wrapping_mul(&self, other: &BigInt) -> BigInt {
+        self * other
+    }
+}
+
+/// `word` as an `i128`, clamped to the nearest end if it does not fit. Used where
+/// only an `i128` can be kept, like error reports and traces.
+pub fn saturate<W: Word>(word: &W) -> i128 {
+    word.to_i128().unwrap_or(if *word < W::from(0) { i128::MIN } else { i128::MAX })
+}
+
+/// What a program did when run with one word type in checked mode. Values are
+/// kept in decimal so runs with different word types can be compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The program or its input holds a number the word type cannot.
+    DoesNotFit,
+    /// Everything output, then whether it halted, needed more input than it was given or faulted.
+    Ran { output: Vec<String>, stop: Result<Event<String>, IntcodeError> },
+}
+
+/// Runs `program`, comma separated as in `parse_input`, with `W` words in checked mode.
+pub fn run_as<W: Word>(program: &str, input: &[&str]) -> Outcome {
+    let program = parse_words::<W>(program);
+    let input: Option<VecDeque<W>> = input.iter().map(|x| x.trim().parse().ok()).collect();
+    let (program, input) = match (program, input) {
+        (Some(program), Some(input)) => (program, input),
+        _ => return Outcome::DoesNotFit,
+    };
+    let mut m = new_machine_with_io(program, input, Vec::new());
+    m.checked = true;
+    let stop = m.run().map(|event| match event {
+        Event::Halted => Event::Halted,
+        _ => Event::NeedsInput,
+    });
+    Outcome::Ran { output: m.output.iter().map(|x| x.to_string()).collect(), stop }
+}
+
+/// Runs `program` once with every word type, narrowest first.
+pub fn compare_widths(program: &str, input: &[&str]) -> Vec<(&'static str, Outcome)> {
+    vec![
+        (i32::NAME, run_as::<i32>(program, input)),
+        (i64::NAME, run_as::<i64>(program, input)),
+        (i128::NAME, run_as::<i128>(program, input)),
+        (BigInt::NAME, run_as::<BigInt>(program, input)),
+    ]
+}
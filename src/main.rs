@@ -1,243 +1,500 @@
 use std::fs;
-use std::sync::mpsc::{Sender, Receiver};
-use std::sync::mpsc;
-use std::time;
-use std::thread;
-use std::collections::HashMap;
-use std::collections::HashSet;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::io::{self, Read, stdin, stdout};
-
-#[derive(Debug)]
-struct Machine {
-    id: i32,
-    ip: i128,
-    ram: HashMap<i128, i128>,
-    inputs: Receiver<i128>,
-    outputs: Sender<i128>,
-    base: i128,
-}
-
-fn read(m: &Machine, index: i128) -> i128 {
-    assert!(index >= 0);
-    let val = *m.ram.get(&index).unwrap_or(&0);
-    // println!("[{}] = {}", index, val);
-    val
-}
-
-fn store(m: &mut Machine, index: i128, value: i128) {
-    assert!(index >= 0);
-    // println!("[{}] := {}", index, value);
-    m.ram.insert(index, value);
-}
-
-fn get_index(m: &Machine, index: i128, flag: i128) -> i128 {
-    if flag == 1 {
-        // immediate value
-        get_immediate(m, index)
-    } else if flag == 0 {
-        // positional value
-        get_arg(m, index, 0)
-    } else if flag == 2 {
-        // relative value
-        // println!("Using relative {}", m.base);
-        get_arg(m, index, m.base)
-    } else {
-        panic!("Invalid flag")
-    }
-}
-
-fn get_arg(m: &Machine, index: i128, offset: i128) -> i128 {
-    read(m, get_immediate(m, index) + offset)
-}
-
-fn get_immediate(m: &Machine, index: i128) -> i128 {
-    read(m, m.ip + index)
-}
+use aoc2019::intcode::{self, Ascii, Cfg, Coverage, Debugger, Event, Filter, Line, Outcome, Recorder, Script, Snapshot, Trace,
+    Transcript, assemble_to_string, compare_widths, decode, disassemble, new_machine, new_machine_with_io, parse_input,
+    preflight};
+use std::env;
+use std::collections::VecDeque;
+use std::io::{self, Write, stdin, stdout};
 
-fn parse_instr(instr: i128) -> (i128, i128, i128, i128) {
-    // println!("instr {}", instr);
-    assert!(instr > 0);
-    (instr % 100,
-        ((instr / 100) % 10),
-        ((instr / 1000) % 10),
-        ((instr / 10000) % 10))
+/// Prints a listing of an Intcode program: `disasm <file>`.
+fn disasm(args: &[String]) -> io::Result<()> {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: disasm <file>");
+            std::process::exit(2);
+        }
+    };
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let out = stdout();
+    let mut out = out.lock();
+    for line in disassemble(&program) {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
 }
 
-fn get_instr(m: &Machine) -> i128 {
-    read(m, m.ip)
+/// Assembles a source file and prints the program: `asm <file>`.
+fn asm(args: &[String]) -> io::Result<()> {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: asm <file>");
+            std::process::exit(2);
+        }
+    };
+    match assemble_to_string(&fs::read_to_string(path)?) {
+        Ok(program) => println!("{}", program),
+        Err(e) => {
+            eprintln!("{}:{}", path, e);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
 }
 
-fn do_addition(m: &mut Machine) -> bool {
-    let (_instr, m1, m2, m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    let arg2 = get_index(m, 2, m2);
-    let output = get_immediate(m, 3) + if m3 == 2 { m.base } else { 0 };
-    store(m, output, arg1 + arg2);
-    if output != m.ip {
-        m.ip += 4;
+/// Runs a program under the debugger, reading commands from stdin: `debug <file> [--history n]`.
+/// `--history` sets how many instructions can be stepped back.
+fn debug(args: &[String]) -> io::Result<()> {
+    let usage = || -> ! {
+        eprintln!("usage: debug <file> [--history n]");
+        std::process::exit(2);
+    };
+    let path = args.first().unwrap_or_else(|| usage());
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let mut debugger = Debugger::new(new_machine(program));
+    match &args[1..] {
+        [] => {}
+        [flag, depth] if flag == "--history" =>
+            debugger.history_depth = depth.parse().unwrap_or_else(|_| usage()),
+        _ => usage(),
+    }
+    println!("{}", debugger.execute("regs").unwrap());
+    let mut last = String::new();
+    loop {
+        print!("(icdb) ");
+        stdout().flush()?;
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        // an empty line repeats the last command
+        if line.trim().is_empty() {
+            line = last.clone();
+        }
+        match debugger.execute(&line) {
+            Some(text) => {
+                if !text.is_empty() {
+                    println!("{}", text);
+                }
+            }
+            None => return Ok(()),
+        }
+        last = line;
     }
-    true
 }
 
-fn do_multiplication(m: &mut Machine) -> bool {
-    let (_instr, m1, m2, m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    let arg2 = get_index(m, 2, m2);
-    let output = get_immediate(m, 3) + if m3 == 2 { m.base } else { 0 };
-    store(m, output, arg1 * arg2);
-    if output != m.ip {
-        m.ip += 4;
+/// `trace record <program> <trace> [input,...]` runs a program on the given input and saves
+/// what it executed. `trace view <trace> [--from ip] [--to ip] [--kind mnemonic,...]` prints
+/// the steps that match.
+fn trace(args: &[String]) -> io::Result<()> {
+    let usage = || -> ! {
+        eprintln!("usage: trace record <program> <trace> [input,...]");
+        eprintln!("       trace view <trace> [--from ip] [--to ip] [--kind mnemonic,...]");
+        std::process::exit(2);
+    };
+    let fail = |e: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    };
+    match args.first().map(|s| s.as_str()) {
+        Some("record") if args.len() == 3 || args.len() == 4 => {
+            let program = parse_input(fs::read_to_string(&args[1])?.trim());
+            let mut m = new_machine_with_io(program, VecDeque::new(), Vec::new());
+            if let Some(input) = args.get(3) {
+                for value in input.split(',') {
+                    m.push_input(value.trim().parse().unwrap_or_else(|_| usage()));
+                }
+            }
+            m.start_trace();
+            let result = m.run();
+            let trace = m.take_trace().unwrap();
+            let output: Vec<String> = m.output.iter().map(|x| x.to_string()).collect();
+            println!("output: {}", output.join(","));
+            match result {
+                Ok(Event::NeedsInput) => println!("stopped waiting for input"),
+                Ok(_) => println!("halted"),
+                Err(e) => println!("{}", e),
+            }
+            trace.save(&args[2]).unwrap_or_else(|e| fail(&e));
+            println!("{} steps written to {}", trace.steps.len(), args[2]);
+        }
+        Some("view") if args.len() >= 2 => {
+            let trace = Trace::load(&args[1]).unwrap_or_else(|e| fail(&e));
+            let mut filter = Filter::default();
+            let mut range = (i128::MIN, i128::MAX);
+            let mut rest = args[2..].iter();
+            while let Some(flag) = rest.next() {
+                let value = rest.next().unwrap_or_else(|| usage());
+                match flag.as_str() {
+                    "--from" => range.0 = value.parse().unwrap_or_else(|_| usage()),
+                    "--to" => range.1 = value.parse().unwrap_or_else(|_| usage()),
+                    "--kind" => filter.kinds.extend(value.split(',').map(|k| k.trim().to_string())),
+                    _ => usage(),
+                }
+            }
+            if range != (i128::MIN, i128::MAX) {
+                filter.range = Some(range);
+            }
+            let out = stdout();
+            let mut out = out.lock();
+            for step in trace.filter(&filter) {
+                writeln!(out, "{}", step)?;
+            }
+        }
+        _ => usage(),
     }
-    true
+    Ok(())
 }
 
-fn do_input(m: &mut Machine) -> bool {
-    // println!("{} {} {}", read(m, m.ip), read(m, m.ip + 1), read(m, m.ip + 2));
-    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m));
-    let pos = get_immediate(m, 1) + if m1 == 2 { m.base } else { 0 };
-    let value = m.inputs.recv().unwrap();
-    // println!("Machine {} read {} from input storing at {}", m.id, value, pos);
-    store(m, pos, value);
-    if pos != m.ip {
-        m.ip += 2;
+/// `profile <program> [input,...] [--top n] [--folded file]` runs a program on the given input
+/// and prints where the time went. `--folded` also writes stacks for flamegraph tools.
+fn profile(args: &[String]) -> io::Result<()> {
+    let usage = || -> ! {
+        eprintln!("usage: profile <program> [input,...] [--top n] [--folded file]");
+        std::process::exit(2);
+    };
+    let path = args.first().unwrap_or_else(|| usage());
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let mut m = new_machine_with_io(program.clone(), VecDeque::new(), Vec::new());
+    let mut top = 20;
+    let mut folded = None;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--top" => top = rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()),
+            "--folded" => folded = Some(rest.next().unwrap_or_else(|| usage())),
+            input => {
+                for value in input.split(',') {
+                    m.push_input(value.trim().parse().unwrap_or_else(|_| usage()));
+                }
+            }
+        }
+    }
+    m.start_profile();
+    let result = m.run();
+    let profile = m.take_profile().unwrap();
+    let output: Vec<String> = m.output.iter().map(|x| x.to_string()).collect();
+    println!("output: {}", output.join(","));
+    match result {
+        Ok(Event::NeedsInput) => println!("stopped waiting for input"),
+        Ok(_) => println!("halted"),
+        Err(e) => println!("{}", e),
     }
-    true
+    println!();
+    print!("{}", profile.report(&program, top));
+    if let Some(file) = folded {
+        fs::write(file, profile.folded())?;
+    }
+    Ok(())
 }
 
-fn do_output(m: &mut Machine) -> bool {
-    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m));
-    let value = get_index(m, 1, m1);
-    // println!("Machine {} outputting {}", m.id, value);
-    m.outputs.send(value).unwrap();
-    m.ip += 2;
-    true
+/// `cfg <program>` prints the control flow graph as Graphviz DOT, and a summary to stderr.
+fn cfg(args: &[String]) -> io::Result<()> {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("usage: cfg <program>");
+            std::process::exit(2);
+        }
+    };
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let cfg = Cfg::build(&program);
+    print!("{}", cfg.to_dot());
+    let unresolved: Vec<String> = cfg.unresolved().iter().map(|a| a.to_string()).collect();
+    eprintln!("{} blocks, {} functions, {} unresolved jumps{}{}", cfg.blocks.len(), cfg.functions.len(),
+        unresolved.len(), if unresolved.is_empty() { "" } else { " at " }, unresolved.join(", "));
+    Ok(())
 }
 
-fn do_jmp(m: &mut Machine, jmp_if: bool) -> bool {
-    let (_instr, m1, m2, _m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    let arg2 = get_index(m, 2, m2);
-    // if jmp_if true then jmp when arg1 is non-zero
-    // if jmp_if false then jmp when arg1 is zero
-    if (arg1 != 0) == jmp_if {
-        m.ip = arg2;
-    } else {
-        m.ip += 3;
-    }
-    true
+/// `decompile <program>` prints the program as pseudocode.
+fn decompile(args: &[String]) -> io::Result<()> {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("usage: decompile <program>");
+            std::process::exit(2);
+        }
+    };
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    print!("{}", intcode::decompile(&program));
+    Ok(())
 }
 
-fn do_lt(m: &mut Machine) -> bool {
-    let (_instr, m1, m2, m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    let arg2 = get_index(m, 2, m2);
-    let output = get_immediate(m, 3) + if m3 == 2 { m.base } else { 0 };
-    store(m, output, if arg1 < arg2 { 1 } else { 0 });
-    if output != m.ip {
-        m.ip += 4;
-    }
-    true
+/// `transpile <program> [path]` prints the program as a Rust module, which reaches this
+/// crate's intcode module by `path`, `aoc2019::intcode` by default.
+fn transpile(args: &[String]) -> io::Result<()> {
+    let (path, intcode) = match args {
+        [path] => (path, "aoc2019::intcode"),
+        [path, intcode] => (path, intcode.as_str()),
+        _ => {
+            eprintln!("usage: transpile <program> [path]");
+            std::process::exit(2);
+        }
+    };
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    print!("{}", intcode::transpile(&program, intcode));
+    Ok(())
 }
 
-fn do_eq(m: &mut Machine) -> bool {
-    let (_instr, m1, m2, m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    let arg2 = get_index(m, 2, m2);
-    let output = get_immediate(m, 3) + if m3 == 2 { m.base } else { 0 };
-    store(m, output, if arg1 == arg2 { 1 } else { 0 });
-    if output != m.ip {
-        m.ip += 4;
+/// `check <program>` lists every reachable instruction a strict machine would refuse to run.
+fn check(args: &[String]) -> io::Result<()> {
+    let path = match args {
+        [path] => path,
+        _ => {
+            eprintln!("usage: check <program>");
+            std::process::exit(2);
+        }
+    };
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let errors = preflight(&program);
+    for error in &errors {
+        println!("{}", error);
     }
-    true
-}
-
-fn do_adjust_base(m: &mut Machine) -> bool {
-    // println!("Adjust base {} {} {}", read(m, m.ip), read(m, m.ip + 1), read(m, m.ip + 2));
-    let (_instr, m1, _m2, _m3) = parse_instr(get_instr(m));
-    let arg1 = get_index(m, 1, m1);
-    m.base += arg1;
-    m.ip += 2;
-    true
+    if !errors.is_empty() {
+        std::process::exit(1);
+    }
+    println!("no invalid instructions found");
+    Ok(())
 }
 
-fn run_one_step(m: &mut Machine) -> bool {
-    // println!("Running {}: {} {}", m.id, m.ip, get_instr(m));
-    let (instr, _m1, _m2, _m3) = parse_instr(get_instr(m));
-    match instr {
-        1 => do_addition(m),
-        2 => do_multiplication(m),
-        3 => do_input(m),
-        4 => do_output(m),
-        5 => do_jmp(m, true),
-        6 => do_jmp(m, false),
-        7 => do_lt(m),
-        8 => do_eq(m),
-        9 => do_adjust_base(m),
-        99 => false,
-        _ => panic!("Invalid instruction")
+/// `patches <program> [input,...]` runs a program and lists every write into code that
+/// had already run, and what the patched instructions became.
+fn patches(args: &[String]) -> io::Result<()> {
+    let usage = || -> ! {
+        eprintln!("usage: patches <program> [input,...]");
+        std::process::exit(2);
+    };
+    let path = args.first().unwrap_or_else(|| usage());
+    let program = parse_input(fs::read_to_string(path)?.trim());
+    let mut m = new_machine(program);
+    for input in &args[1..] {
+        for value in input.split(',') {
+            m.push_input(value.trim().parse().unwrap_or_else(|_| usage()));
+        }
+    }
+    m.start_code_watch();
+    let result = m.run();
+    let watch = m.take_code_watch().unwrap();
+    match result {
+        Ok(Event::NeedsInput) => println!("stopped waiting for input after {} steps", m.steps),
+        Ok(_) => println!("halted after {} steps", m.steps),
+        Err(e) => println!("{}", e),
     }
+    println!("{} writes into executed code", watch.patches.len());
+    let show = |address| decode(&m.ram, address).map(|i| i.to_string()).unwrap_or_else(|| "???".to_string());
+    for patch in &watch.patches {
+        println!("  {}", patch);
+    }
+    for address in watch.patched_instructions() {
+        println!("  {:>6}: now {}", address, show(address));
+    }
+    Ok(())
 }
 
-fn vec_to_map(v: Vec<i128>) -> HashMap<i128, i128> {
-    let mut result = HashMap::new();
-    for i in 0..v.len() {
-        result.insert(i as i128, v[i]);
+/// `coverage <program> <listing> <lcov> [input,...]...` runs a program once per input list and
+/// writes its disassembly to `listing` and which lines of it ran, as lcov, to `lcov`.
+fn coverage(args: &[String]) -> io::Result<()> {
+    let usage = || -> ! {
+        eprintln!("usage: coverage <program> <listing> <lcov> [input,...]...");
+        std::process::exit(2);
+    };
+    if args.len() < 3 {
+        usage();
+    }
+    let program = parse_input(fs::read_to_string(&args[0])?.trim());
+    let runs: Vec<&str> = if args.len() == 3 { vec![""] } else { args[3..].iter().map(|s| s.as_str()).collect() };
+    let mut coverage = Coverage::default();
+    for input in runs {
+        let mut m = new_machine_with_io(program.clone(), VecDeque::new(), Vec::new());
+        for value in input.split(',').filter(|v| !v.trim().is_empty()) {
+            m.push_input(value.trim().parse().unwrap_or_else(|_| usage()));
+        }
+        m.start_profile();
+        if let Err(e) = m.run() {
+            eprintln!("run with input '{}': {}", input, e);
+        }
+        coverage.add(&m.take_profile().unwrap());
     }
-    result
+    let listing = coverage.listing(&program);
+    let text: String = listing.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(&args[1], text)?;
+    let lcov = coverage.lcov(&listing, &args[1]);
+    fs::write(&args[2], &lcov)?;
+    let lines = listing.iter().filter(|line| match line {
+        Line::Instruction(instr) => coverage.hits.contains_key(&instr.address),
+        Line::Data { .. } => false,
+    }).count();
+    println!("{} instructions covered", lines);
+    Ok(())
 }
 
-fn run_machine(mut m: Machine) -> thread::JoinHandle<Machine> {
-    thread::spawn(move || {
-        while run_one_step(&mut m) {
+/// `widths <program> [input,...]` runs a program with every word type in checked mode and
+/// reports whether they all output the same.
+fn widths(args: &[String]) -> io::Result<()> {
+    let path = match args.first() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: widths <program> [input,...]");
+            std::process::exit(2);
+        }
+    };
+    let program = fs::read_to_string(path)?;
+    let input: Vec<&str> = args[1..].iter().flat_map(|arg| arg.split(',')).collect();
+    let outcomes = compare_widths(program.trim(), &input);
+    for (name, outcome) in &outcomes {
+        match outcome {
+            Outcome::DoesNotFit => println!("{:>6}: program or input does not fit", name),
+            Outcome::Ran { output, stop } => {
+                let stop = match stop {
+                    Ok(Event::Halted) => "halted".to_string(),
+                    Ok(_) => "needs input".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                println!("{:>6}: {} ({})", name, output.join(","), stop);
+            }
         }
-        m
-    })
+    }
+    if outcomes.iter().all(|(_, outcome)| *outcome == outcomes[0].1) {
+        println!("all widths agree");
+    } else {
+        println!("widths disagree");
+    }
+    Ok(())
 }
 
-fn new_machine(program: HashMap<i128, i128>) -> (Machine, Sender<i128>, Receiver<i128>) {
-    let (my_input, input) = mpsc::channel();
-    let (output, my_output) = mpsc::channel();
-    let mac = Machine {
-        id: 0,
-        ip: 0,
-        ram: program,
-        inputs: input,
-        outputs: output,
-        base: 0,
-    };
-    (mac, my_input, my_output)
+/// `replay <program> <transcript>` checks that a program does exactly what a recorded session did.
+fn replay(args: &[String]) -> io::Result<()> {
+    if args.len() != 2 {
+        eprintln!("usage: replay <program> <transcript>");
+        std::process::exit(2);
+    }
+    let program = parse_input(fs::read_to_string(&args[0])?.trim());
+    let transcript = Transcript::load(&args[1]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    match intcode::replay(&program, &transcript) {
+        Ok(()) => println!("{} entries replayed, outputs match", transcript.entries.len()),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
 }
 
-fn parse_input(input: &str) -> HashMap<i128, i128> {
-    let v: Vec<i128> = input.split(",").map(|x| x.parse::<i128>().unwrap()).collect();
-    vec_to_map(v)
+/// `expect <program> <script>` runs an expect script against a text program, printing
+/// what the program printed with `--echo`.
+fn expect(args: &[String]) -> io::Result<()> {
+    let echo = args.iter().any(|a| a == "--echo");
+    let args: Vec<&String> = args.iter().filter(|a| *a != "--echo").collect();
+    if args.len() != 2 {
+        eprintln!("usage: expect <program> <script> [--echo]");
+        std::process::exit(2);
+    }
+    let program = parse_input(fs::read_to_string(args[0])?.trim());
+    let script = Script::parse(&fs::read_to_string(args[1])?).unwrap_or_else(|e| {
+        eprintln!("{}:{}", args[1], e);
+        std::process::exit(1);
+    });
+    let run = script.run(&mut new_machine(program));
+    if echo {
+        print!("{}", run.output);
+    }
+    for line in &run.printed {
+        println!("{}", line);
+    }
+    if let Err(e) = run.result {
+        println!("{}", e);
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
-fn main() -> io::Result<()> {
+/// Plays day 25 on the terminal. `save <file>` and `load <file>` snapshot the game and
+/// `q` quits. With `record`, the session is written there as a transcript when the game ends.
+fn play(record: Option<&String>) -> io::Result<()> {
     let contents = fs::read_to_string("input25.txt")
         .expect("File reading failed");
     let arr = parse_input(contents.trim());
-    let (mac, my_input, my_output) = new_machine(arr);
-    let thread = run_machine(mac);
-    thread::spawn(move || {
-        while let Ok(x) = my_output.recv() {
-            print!("{}", x as u8 as char);
-        }
-    });
-    let mut buffer = String::new();
+    let recorder = Recorder::new();
+    let mut game = Ascii::new(new_machine_with_io(arr, recorder.record(0, VecDeque::new()), recorder.record(0, ())));
     loop {
-        // stdout().flush();
-        stdin().read_line(&mut buffer).unwrap();
-        if buffer == "q" {
+        let screen = match game.read_until_prompt() {
+            Ok(screen) => screen,
+            Err(e) => {
+                println!("{}", e);
+                break;
+            }
+        };
+        for line in &screen.lines {
+            println!("{}", line);
+        }
+        for value in &screen.values {
+            println!("{}", value);
+        }
+        if screen.stop != Event::NeedsInput {
+            break;
+        }
+        stdout().flush()?;
+        let mut line = String::new();
+        if stdin().read_line(&mut line)? == 0 {
             break;
         }
-        for c in buffer.chars() {
-            my_input.send(c as i128).unwrap();
+        let line = line.trim_end();
+        let words: Vec<&str> = line.splitn(2, ' ').collect();
+        match words[..] {
+            ["q"] => break,
+            ["save", path] => match Snapshot::of(&game.machine, game.machine.input.inner.clone()).save(path) {
+                Ok(()) => println!("Saved to {}", path),
+                Err(e) => println!("{}", e),
+            },
+            // the transcript would not replay from the start any more
+            ["load", _] if record.is_some() => println!("Cannot load while recording"),
+            ["load", path] => match Snapshot::load(path) {
+                Ok(snapshot) => {
+                    let input = recorder.record(0, snapshot.input.clone());
+                    game.machine = snapshot.restore_with_io(input, recorder.record(0, ()));
+                    println!("Loaded {}", path);
+                }
+                Err(e) => println!("{}", e),
+            },
+            _ => game.send_line(line),
+        }
+    }
+    if let Some(path) = record {
+        match recorder.transcript().save(path) {
+            Ok(()) => println!("Recorded to {}", path),
+            Err(e) => println!("{}", e),
         }
     }
-
-    thread.join().unwrap();
     Ok(())
 }
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|s| s.as_str()) {
+        Some("disasm") => disasm(&args[1..]),
+        Some("asm") => asm(&args[1..]),
+        Some("debug") => debug(&args[1..]),
+        Some("trace") => trace(&args[1..]),
+        Some("profile") => profile(&args[1..]),
+        Some("coverage") => coverage(&args[1..]),
+        Some("widths") => widths(&args[1..]),
+        Some("replay") => replay(&args[1..]),
+        Some("expect") => expect(&args[1..]),
+        Some("patches") => patches(&args[1..]),
+        Some("check") => check(&args[1..]),
+        Some("cfg") => cfg(&args[1..]),
+        Some("decompile") => decompile(&args[1..]),
+        Some("transpile") => transpile(&args[1..]),
+        Some("record") if args.len() == 2 => play(args.get(1)),
+        Some(other) => {
+            eprintln!("unknown command {}, expected disasm, asm, debug, trace, profile, coverage, widths, \
+                record <transcript>, replay, expect, patches, check, cfg, decompile, transpile or nothing to play day 25", other);
+            std::process::exit(2);
+        }
+        None => play(None),
+    }
+}